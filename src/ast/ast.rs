@@ -0,0 +1,839 @@
+use pest::Parser;
+use pest::iterators::{Pair, Pairs};
+use pest::pratt_parser::{Assoc, Op, PrattParser};
+use num_bigint::BigInt;
+use crate::*;
+use parser::{BaikLexer, Rule};
+use value::Decimal;
+use Error;
+
+/// Location of a node in the source, `start` and `end` are byte offsets.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    fn from_pair(pair: &Pair<Rule>) -> Span {
+        let span = pair.as_span();
+        let (line, column) = span.start_pos().line_col();
+        Span {
+            start: span.start(),
+            end: span.end(),
+            line,
+            column,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pub stmts: Vec<Stmt>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+    Expr(Expr),
+    Type(TypeDef),
+    Trait(TraitDef),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    Integer(u64),
+    /// An integer literal too large for a `u64`.
+    BigInteger(BigInt),
+    Float(f64),
+    /// `12.50d`
+    Decimal(Decimal),
+    String(String),
+    Boolean(bool),
+    Atom(String),
+    TypeName(String),
+    Array(Vec<Expr>),
+    Map(Vec<(MapKey, Expr)>),
+    Constructor(String, Vec<(String, Expr)>),
+    Local(String),
+    PropertyGet(String),
+    PropertySet(Vec<(String, Expr)>),
+    Declaration(String, Box<Expr>),
+    Let(String, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Unary(UnaryOp, Box<Expr>),
+    Call(Box<Expr>, Vec<Expr>),
+    MethodCall(Box<Expr>, String, Vec<Expr>),
+    /// `a[i]`
+    Index(Box<Expr>, Box<Expr>),
+    /// `m.k`
+    Field(Box<Expr>, String),
+    /// `a[i] = v` or `m.k = v`
+    Assign(Box<Expr>, Box<Expr>),
+    If(Box<If>),
+    While(Box<Expr>, Block),
+    For(Box<For>),
+    Range(Box<Expr>, Box<Expr>),
+    Return(Option<Box<Expr>>),
+    Break,
+    Continue,
+    Function(FunctionDef),
+    Tulis(Vec<Expr>),
+}
+
+impl Expr {
+    /// Direct sub-expressions, including those of nested blocks.
+    pub fn children(&self) -> Vec<&Expr> {
+        let mut children = Vec::new();
+        match self.kind {
+            ExprKind::Array(ref items) | ExprKind::Tulis(ref items) => children.extend(items),
+            ExprKind::Map(ref pairs) => {
+                for (key, value) in pairs {
+                    if let MapKey::Expr(ref key) = *key {
+                        children.push(key);
+                    }
+                    children.push(value);
+                }
+            }
+            ExprKind::Constructor(_, ref props) | ExprKind::PropertySet(ref props) => {
+                children.extend(props.iter().map(|prop| &prop.1))
+            }
+            ExprKind::Declaration(_, ref value) |
+            ExprKind::Let(_, ref value) |
+            ExprKind::Unary(_, ref value) |
+            ExprKind::Field(ref value, _) => children.push(value),
+            ExprKind::Binary(_, ref lhs, ref rhs) |
+            ExprKind::Range(ref lhs, ref rhs) |
+            ExprKind::Index(ref lhs, ref rhs) |
+            ExprKind::Assign(ref lhs, ref rhs) => {
+                children.push(lhs);
+                children.push(rhs);
+            }
+            ExprKind::Call(ref callee, ref args) | ExprKind::MethodCall(ref callee, _, ref args) => {
+                children.push(callee);
+                children.extend(args);
+            }
+            ExprKind::If(ref if_expr) => {
+                children.push(&if_expr.condition);
+                children.extend(&if_expr.positive.exprs);
+                for (condition, block) in &if_expr.branches {
+                    children.push(condition);
+                    children.extend(&block.exprs);
+                }
+                if let Some(ref block) = if_expr.negative {
+                    children.extend(&block.exprs);
+                }
+            }
+            ExprKind::While(ref condition, ref body) => {
+                children.push(condition);
+                children.extend(&body.exprs);
+            }
+            ExprKind::For(ref for_loop) => {
+                children.push(&for_loop.collection);
+                children.extend(&for_loop.body.exprs);
+            }
+            ExprKind::Function(ref function) => {
+                for clause in &function.clauses {
+                    children.extend(&clause.body.exprs);
+                }
+            }
+            ExprKind::Return(Some(ref value)) => children.push(value),
+            _ => (),
+        }
+        children
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MapKey {
+    Keyword(String),
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Pow,
+    Mul,
+    Div,
+    /// `//`, rounds toward zero like `%` does.
+    IntDiv,
+    Rem,
+    Add,
+    Sub,
+    Shl,
+    Shr,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Ne,
+    Eq,
+    And,
+    Or,
+    BitAnd,
+    BitOr,
+    BitXor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOp {
+    Neg,
+    Plus,
+    Not,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub exprs: Vec<Expr>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct If {
+    pub condition: Expr,
+    pub positive: Block,
+    pub branches: Vec<(Expr, Block)>,
+    pub negative: Option<Block>,
+}
+
+/// `untuk item dalam koleksi { ... }`, `key` is set for the `untuk k, v dalam ...` form.
+#[derive(Debug, Clone, PartialEq)]
+pub struct For {
+    pub key: Option<String>,
+    pub item: String,
+    pub collection: Expr,
+    pub body: Block,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionDef {
+    /// `None` for anonymous functions like `fungsi (x) { x * 2 }`.
+    pub name: Option<String>,
+    pub clauses: Vec<FunctionClause>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionClause {
+    pub args: Vec<String>,
+    pub body: Block,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeDef {
+    pub name: String,
+    pub props: Vec<String>,
+    pub methods: Vec<MethodDef>,
+    pub impls: Vec<ImplDef>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraitDef {
+    pub name: String,
+    pub requires: Vec<String>,
+    pub methods: Vec<MethodDef>,
+    pub specs: Vec<MethodSpec>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImplDef {
+    pub trait_name: String,
+    pub methods: Vec<MethodDef>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MethodKind {
+    Public,
+    Private,
+    Static,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodDef {
+    pub kind: MethodKind,
+    pub name: String,
+    pub predicate: bool,
+    pub args: Vec<String>,
+    pub body: Block,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodSpec {
+    pub kind: MethodKind,
+    pub name: String,
+    pub predicate: bool,
+    pub args: Vec<String>,
+    pub returns: Option<Vec<String>>,
+    pub span: Span,
+}
+
+/// Parses a script (`Rule::input`) into a `Program`.
+pub fn parse(source: &str) -> Result<Program, Error> {
+    let program = build_program(BaikLexer::parse(Rule::input, source)
+        .map_err(|e| Error::Syntax(e.to_string()))?)?;
+    validate(&program)?;
+    Ok(program)
+}
+
+/// Parses a definition file (`Rule::file`) into a `Program`.
+pub fn parse_file(source: &str) -> Result<Program, Error> {
+    let program = build_program(BaikLexer::parse(Rule::file, source)
+        .map_err(|e| Error::Syntax(e.to_string()))?)?;
+    validate(&program)?;
+    Ok(program)
+}
+
+/// Parses a single expression (`Rule::expression_input`), as given to `baik::Expr`.
+pub fn parse_expression(source: &str) -> Result<Expr, Error> {
+    let pair = BaikLexer::parse(Rule::expression_input, source)
+        .map_err(|e| Error::Syntax(e.to_string()))?
+        .next()
+        .unwrap();
+    let expr = build_expr(&create_pratt_parser(), pair)?;
+    check_control_flow(&expr, false, false)?;
+    Ok(expr)
+}
+
+/// Rejects `kembali`, `berhenti` and `lanjut` outside of the bodies they can leave.
+pub fn validate(program: &Program) -> Result<(), Error> {
+    for stmt in &program.stmts {
+        match *stmt {
+            Stmt::Expr(ref expr) => check_control_flow(expr, false, false)?,
+            Stmt::Type(ref typedef) => {
+                let methods = typedef.impls.iter().flat_map(|i| &i.methods);
+                for method in typedef.methods.iter().chain(methods) {
+                    check_block_control_flow(&method.body, true, false)?;
+                }
+            }
+            Stmt::Trait(ref traitdef) => {
+                for method in &traitdef.methods {
+                    check_block_control_flow(&method.body, true, false)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_block_control_flow(block: &Block, in_function: bool, in_loop: bool) -> Result<(), Error> {
+    for expr in &block.exprs {
+        check_control_flow(expr, in_function, in_loop)?;
+    }
+    Ok(())
+}
+
+fn check_control_flow(expr: &Expr, in_function: bool, in_loop: bool) -> Result<(), Error> {
+    match expr.kind {
+        ExprKind::Return(_) if !in_function => Err(Error::ReturnOutsideFunction(expr.span.line)),
+        ExprKind::Break if !in_loop => Err(Error::BreakOutsideLoop(expr.span.line)),
+        ExprKind::Continue if !in_loop => Err(Error::ContinueOutsideLoop(expr.span.line)),
+        ExprKind::Function(ref function) => {
+            for clause in &function.clauses {
+                check_block_control_flow(&clause.body, true, false)?;
+            }
+            Ok(())
+        }
+        ExprKind::While(ref condition, ref body) => {
+            check_control_flow(condition, in_function, in_loop)?;
+            check_block_control_flow(body, in_function, true)
+        }
+        ExprKind::For(ref for_loop) => {
+            check_control_flow(&for_loop.collection, in_function, in_loop)?;
+            check_block_control_flow(&for_loop.body, in_function, true)
+        }
+        _ => {
+            for child in expr.children() {
+                check_control_flow(child, in_function, in_loop)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn build_program(pairs: Pairs<Rule>) -> Result<Program, Error> {
+    let pratt = create_pratt_parser();
+    let mut stmts = Vec::new();
+    for pair in pairs {
+        match pair.as_rule() {
+            Rule::EOI => break,
+            Rule::typedef => stmts.push(Stmt::Type(build_typedef(&pratt, pair)?)),
+            Rule::traitdef => stmts.push(Stmt::Trait(build_traitdef(&pratt, pair)?)),
+            _ => stmts.push(Stmt::Expr(build_expr(&pratt, pair)?)),
+        }
+    }
+    Ok(Program { stmts })
+}
+
+fn create_pratt_parser() -> PrattParser<Rule> {
+    // Lowest priority first.
+    PrattParser::new()
+        .op(Op::infix(Rule::logical_or, Assoc::Left))
+        .op(Op::infix(Rule::logical_and, Assoc::Left))
+        .op(Op::infix(Rule::equal, Assoc::Left) | Op::infix(Rule::not_equal, Assoc::Left) |
+            Op::infix(Rule::less_than, Assoc::Left) | Op::infix(Rule::less_than_or_equal, Assoc::Left) |
+            Op::infix(Rule::greater_than, Assoc::Left) |
+            Op::infix(Rule::greater_than_or_equal, Assoc::Left))
+        .op(Op::infix(Rule::bitwise_or, Assoc::Left))
+        .op(Op::infix(Rule::bitwise_xor, Assoc::Left))
+        .op(Op::infix(Rule::bitwise_and, Assoc::Left))
+        .op(Op::infix(Rule::shift_left, Assoc::Left) | Op::infix(Rule::shift_right, Assoc::Left))
+        .op(Op::infix(Rule::plus, Assoc::Left) | Op::infix(Rule::minus, Assoc::Left))
+        .op(Op::infix(Rule::multiply, Assoc::Left) | Op::infix(Rule::divide, Assoc::Left) |
+            Op::infix(Rule::integer_divide, Assoc::Left) | Op::infix(Rule::modulus, Assoc::Left))
+        .op(Op::infix(Rule::exponent, Assoc::Right))
+}
+
+fn binary_op(rule: Rule) -> BinaryOp {
+    match rule {
+        Rule::exponent => BinaryOp::Pow,
+        Rule::multiply => BinaryOp::Mul,
+        Rule::divide => BinaryOp::Div,
+        Rule::integer_divide => BinaryOp::IntDiv,
+        Rule::modulus => BinaryOp::Rem,
+        Rule::plus => BinaryOp::Add,
+        Rule::minus => BinaryOp::Sub,
+        Rule::shift_left => BinaryOp::Shl,
+        Rule::shift_right => BinaryOp::Shr,
+        Rule::less_than => BinaryOp::Lt,
+        Rule::less_than_or_equal => BinaryOp::Le,
+        Rule::greater_than => BinaryOp::Gt,
+        Rule::greater_than_or_equal => BinaryOp::Ge,
+        Rule::not_equal => BinaryOp::Ne,
+        Rule::equal => BinaryOp::Eq,
+        Rule::logical_and => BinaryOp::And,
+        Rule::logical_or => BinaryOp::Or,
+        Rule::bitwise_and => BinaryOp::BitAnd,
+        Rule::bitwise_or => BinaryOp::BitOr,
+        Rule::bitwise_xor => BinaryOp::BitXor,
+        _ => unreachable!("not a binary operator: {:?}", rule),
+    }
+}
+
+fn build_exprs(pratt: &PrattParser<Rule>, pairs: Pairs<Rule>) -> Result<Vec<Expr>, Error> {
+    pairs.map(|pair| build_expr(pratt, pair)).collect()
+}
+
+fn build_block(pratt: &PrattParser<Rule>, pair: Pair<Rule>) -> Result<Block, Error> {
+    let span = Span::from_pair(&pair);
+    Ok(Block {
+        exprs: build_exprs(pratt, pair.into_inner())?,
+        span,
+    })
+}
+
+fn build_args(pair: Pair<Rule>) -> Vec<String> {
+    pair.into_inner().map(|arg| arg.as_str().to_owned()).collect()
+}
+
+fn build_keyword(pair: &Pair<Rule>) -> String {
+    pair.as_str().trim_end_matches(':').trim_end().to_owned()
+}
+
+fn build_properties(pratt: &PrattParser<Rule>,
+                    pairs: Pairs<Rule>)
+                    -> Result<Vec<(String, Expr)>, Error> {
+    let mut properties = Vec::new();
+    for property in pairs {
+        let mut inner = property.into_inner();
+        let key = build_keyword(&inner.next().unwrap());
+        properties.push((key, build_expr(pratt, inner.next().unwrap())?));
+    }
+    Ok(properties)
+}
+
+fn build_expr(pratt: &PrattParser<Rule>, pair: Pair<Rule>) -> Result<Expr, Error> {
+    let span = Span::from_pair(&pair);
+    let kind = match pair.as_rule() {
+        Rule::infix | Rule::instance_infix | Rule::power => {
+            return pratt.map_primary(|pair| build_expr(pratt, pair))
+                .map_infix(|lhs, op, rhs| {
+                    let (lhs, rhs) = (lhs?, rhs?);
+                    let span = Span {
+                        start: lhs.span.start,
+                        end: rhs.span.end,
+                        ..lhs.span
+                    };
+                    Ok(Expr {
+                        kind: ExprKind::Binary(binary_op(op.as_rule()), Box::new(lhs), Box::new(rhs)),
+                        span,
+                    })
+                })
+                .parse(pair.into_inner())
+        }
+        Rule::declaration => {
+            let mut inner = pair.into_inner().peekable();
+            let is_let = inner.peek().unwrap().as_rule() == Rule::let_keyword;
+            if is_let {
+                inner.next();
+            }
+            let ident = inner.next().unwrap().as_str().to_owned();
+            inner.next(); // assign
+            let value = Box::new(build_expr(pratt, inner.next().unwrap())?);
+            if is_let {
+                ExprKind::Let(ident, value)
+            } else {
+                ExprKind::Declaration(ident, value)
+            }
+        }
+        Rule::unary => {
+            let mut inner = pair.into_inner();
+            let op = match inner.next().unwrap().as_rule() {
+                Rule::minus => UnaryOp::Neg,
+                Rule::plus => UnaryOp::Plus,
+                _ => UnaryOp::Not,
+            };
+            ExprKind::Unary(op, Box::new(build_expr(pratt, inner.next().unwrap())?))
+        }
+        Rule::primary => {
+            let mut inner = pair.into_inner();
+            let mut receiver = build_expr(pratt, inner.next().unwrap())?;
+            for suffix in inner {
+                let suffix_span = Span { end: suffix.as_span().end(), ..receiver.span };
+                let rule = suffix.as_rule();
+                let mut suffix = suffix.into_inner();
+                let kind = match rule {
+                    Rule::method_suffix => {
+                        let method = suffix.next().unwrap().as_str().to_owned();
+                        let args = build_call_arguments(pratt, suffix)?;
+                        ExprKind::MethodCall(Box::new(receiver), method, args)
+                    }
+                    Rule::index_suffix => {
+                        let index = build_expr(pratt, suffix.next().unwrap())?;
+                        ExprKind::Index(Box::new(receiver), Box::new(index))
+                    }
+                    Rule::call_suffix => {
+                        ExprKind::Call(Box::new(receiver), build_call_arguments(pratt, suffix)?)
+                    }
+                    _ => ExprKind::Field(Box::new(receiver), suffix.next().unwrap().as_str().to_owned()),
+                };
+                receiver = Expr { kind, span: suffix_span };
+            }
+            return Ok(Expr { span, ..receiver });
+        }
+        Rule::operand => {
+            let mut inner = pair.into_inner();
+            let operand_pair = inner.next().unwrap();
+            let text = operand_pair.as_str().trim();
+            let operand = build_expr(pratt, operand_pair)?;
+            match inner.next() {
+                None => return Ok(operand),
+                Some(ref next) if next.as_rule() == Rule::assign => {
+                    if !is_assignable(&operand) {
+                        return Err(Error::Syntax(format!("can not assign to {}", text)));
+                    }
+                    let value = build_expr(pratt, inner.next().unwrap())?;
+                    ExprKind::Assign(Box::new(operand), Box::new(value))
+                }
+                Some(end) => ExprKind::Range(Box::new(operand), Box::new(build_expr(pratt, end)?)),
+            }
+        }
+        Rule::if_expression => {
+            let mut inner = pair.into_inner();
+            let condition = build_expr(pratt, inner.next().unwrap())?;
+            let positive = build_block(pratt, inner.next().unwrap())?;
+            let mut branches = Vec::new();
+            let mut negative = None;
+            for branch in inner {
+                match branch.as_rule() {
+                    Rule::if_else => {
+                        let mut inner = branch.into_inner();
+                        inner.next(); // if_else_keyword
+                        let condition = build_expr(pratt, inner.next().unwrap())?;
+                        branches.push((condition, build_block(pratt, inner.next().unwrap())?));
+                    }
+                    _ => negative = Some(build_block(pratt, branch)?),
+                }
+            }
+            ExprKind::If(Box::new(If {
+                condition,
+                positive,
+                branches,
+                negative,
+            }))
+        }
+        Rule::while_loop => {
+            let mut inner = pair.into_inner();
+            let condition = build_expr(pratt, inner.next().unwrap())?;
+            ExprKind::While(Box::new(condition), build_block(pratt, inner.next().unwrap())?)
+        }
+        Rule::for_loop => {
+            let mut inner = pair.into_inner().peekable();
+            let mut item = inner.next().unwrap().as_str().to_owned();
+            let mut key = None;
+            if inner.peek().unwrap().as_rule() == Rule::ident {
+                key = Some(item);
+                item = inner.next().unwrap().as_str().to_owned();
+            }
+            let collection = build_expr(pratt, inner.next().unwrap())?;
+            let body = build_block(pratt, inner.next().unwrap())?;
+            ExprKind::For(Box::new(For {
+                key,
+                item,
+                collection,
+                body,
+            }))
+        }
+        Rule::return_expression => {
+            match pair.into_inner().next().and_then(|value| value.into_inner().next()) {
+                Some(value) => ExprKind::Return(Some(Box::new(build_expr(pratt, value)?))),
+                None => ExprKind::Return(None),
+            }
+        }
+        Rule::break_expression => ExprKind::Break,
+        Rule::continue_expression => ExprKind::Continue,
+        Rule::function => ExprKind::Function(build_function(pratt, pair)?),
+        Rule::tulis => ExprKind::Tulis(build_exprs(pratt, pair.into_inner())?),
+        Rule::local => ExprKind::Local(pair.as_str().to_owned()),
+        Rule::property_get => ExprKind::PropertyGet(pair.as_str()[1..].to_owned()),
+        Rule::property_set => ExprKind::PropertySet(build_properties(pratt, pair.into_inner())?),
+        Rule::constructor => {
+            let mut inner = pair.into_inner();
+            let typename = inner.next().unwrap().as_str().to_owned();
+            ExprKind::Constructor(typename, build_properties(pratt, inner)?)
+        }
+        Rule::array => ExprKind::Array(build_exprs(pratt, pair.into_inner())?),
+        Rule::map => {
+            let mut pairs = Vec::new();
+            for map_pair in pair.into_inner() {
+                let mut inner = map_pair.into_inner();
+                let key = inner.next().unwrap();
+                let key = if key.as_rule() == Rule::keyword {
+                    MapKey::Keyword(build_keyword(&key))
+                } else {
+                    MapKey::Expr(build_expr(pratt, key)?)
+                };
+                pairs.push((key, build_expr(pratt, inner.next().unwrap())?));
+            }
+            ExprKind::Map(pairs)
+        }
+        Rule::typename => ExprKind::TypeName(pair.as_str().to_owned()),
+        Rule::atom => ExprKind::Atom(pair.as_str()[1..].to_owned()),
+        Rule::string => {
+            ExprKind::String(unescape(pair.into_inner().next().map(|p| p.as_str()).unwrap_or("")))
+        }
+        Rule::integer => parse_integer(pair.into_inner().next().unwrap())?,
+        Rule::float => {
+            let raw = pair.as_str().replace("_", "");
+            ExprKind::Float(raw.parse::<f64>().map_err(|_| Error::ExpectedNumber)?)
+        }
+        Rule::decimal => {
+            let raw = pair.as_str();
+            ExprKind::Decimal(raw[..raw.len() - 1].parse()?)
+        }
+        Rule::boolean => {
+            ExprKind::Boolean(pair.into_inner().next().unwrap().as_rule() == Rule::boolean_true)
+        }
+        rule => return Err(Error::UnsupportedExpression(format!("{:?}", rule))),
+    };
+
+    Ok(Expr { kind, span })
+}
+
+/// Only indexes and fields of a variable or an `@` property can be assigned to.
+fn is_assignable(target: &Expr) -> bool {
+    fn is_root(expr: &Expr) -> bool {
+        match expr.kind {
+            ExprKind::Local(_) | ExprKind::PropertyGet(_) => true,
+            ExprKind::Index(ref target, _) | ExprKind::Field(ref target, _) => is_root(target),
+            _ => false,
+        }
+    }
+
+    match target.kind {
+        ExprKind::Index(ref target, _) | ExprKind::Field(ref target, _) => is_root(target),
+        _ => false,
+    }
+}
+
+fn build_call_arguments(pratt: &PrattParser<Rule>,
+                        pairs: Pairs<Rule>)
+                        -> Result<Vec<Expr>, Error> {
+    pairs.map(|arg| build_expr(pratt, arg.into_inner().next().unwrap())).collect()
+}
+
+fn build_function(pratt: &PrattParser<Rule>, pair: Pair<Rule>) -> Result<FunctionDef, Error> {
+    let span = Span::from_pair(&pair);
+    let mut inner = pair.into_inner().peekable();
+    let name = match inner.peek() {
+        Some(pair) if pair.as_rule() == Rule::ident => Some(pair.as_str().to_owned()),
+        _ => None,
+    };
+    if name.is_some() {
+        inner.next();
+    }
+    let mut clauses = Vec::new();
+    for clause in inner {
+        let span = Span::from_pair(&clause);
+        let mut clause = clause.into_inner();
+        let args = build_args(clause.next().unwrap());
+        let body = build_block(pratt, clause.next().unwrap())?;
+        clauses.push(FunctionClause { args, body, span });
+    }
+    Ok(FunctionDef { name, clauses, span })
+}
+
+fn build_method_name(pair: &Pair<Rule>) -> (String, bool) {
+    (pair.as_str().to_owned(), pair.as_rule() == Rule::methodnamewithpredicate)
+}
+
+fn build_method(pratt: &PrattParser<Rule>, pair: Pair<Rule>) -> Result<MethodDef, Error> {
+    let span = Span::from_pair(&pair);
+    let kind = match pair.as_rule() {
+        Rule::defprivatemethod => MethodKind::Private,
+        Rule::defstaticmethod => MethodKind::Static,
+        _ => MethodKind::Public,
+    };
+    let mut inner = pair.into_inner();
+    let (name, predicate) = build_method_name(&inner.next().unwrap());
+    let args = build_args(inner.next().unwrap());
+    let body = build_block(pratt, inner.next().unwrap())?;
+    Ok(MethodDef {
+        kind,
+        name,
+        predicate,
+        args,
+        body,
+        span,
+    })
+}
+
+fn build_method_spec(pair: Pair<Rule>) -> MethodSpec {
+    let span = Span::from_pair(&pair);
+    let kind = match pair.as_rule() {
+        Rule::defstaticspec => MethodKind::Static,
+        _ => MethodKind::Public,
+    };
+    let mut inner = pair.into_inner();
+    let (name, predicate) = build_method_name(&inner.next().unwrap());
+    let args = build_args(inner.next().unwrap());
+    let returns = inner.next().map(|rval| build_typespec(rval.into_inner().next().unwrap()));
+    MethodSpec {
+        kind,
+        name,
+        predicate,
+        args,
+        returns,
+        span,
+    }
+}
+
+fn build_typespec(pair: Pair<Rule>) -> Vec<String> {
+    pair.into_inner().map(|typename| typename.as_str().to_owned()).collect()
+}
+
+fn build_typedef(pratt: &PrattParser<Rule>, pair: Pair<Rule>) -> Result<TypeDef, Error> {
+    let span = Span::from_pair(&pair);
+    let mut inner = pair.into_inner();
+    let name = inner.next().unwrap().as_str().to_owned();
+    let props = build_args(inner.next().unwrap());
+    let mut methods = Vec::new();
+    let mut impls = Vec::new();
+    for item in inner.next().unwrap().into_inner() {
+        if item.as_rule() == Rule::impldef {
+            let span = Span::from_pair(&item);
+            let mut inner = item.into_inner();
+            let trait_name = inner.next().unwrap().as_str().to_owned();
+            let methods = inner.next()
+                .unwrap()
+                .into_inner()
+                .map(|method| build_method(pratt, method))
+                .collect::<Result<Vec<_>, Error>>()?;
+            impls.push(ImplDef {
+                trait_name,
+                methods,
+                span,
+            });
+        } else {
+            methods.push(build_method(pratt, item)?);
+        }
+    }
+    Ok(TypeDef {
+        name,
+        props,
+        methods,
+        impls,
+        span,
+    })
+}
+
+fn build_traitdef(pratt: &PrattParser<Rule>, pair: Pair<Rule>) -> Result<TraitDef, Error> {
+    let span = Span::from_pair(&pair);
+    let mut inner = pair.into_inner();
+    let name = inner.next().unwrap().as_str().to_owned();
+    let requires = inner.next()
+        .unwrap()
+        .into_inner()
+        .next()
+        .map(build_typespec)
+        .unwrap_or_default();
+    let mut methods = Vec::new();
+    let mut specs = Vec::new();
+    for item in inner.next().unwrap().into_inner() {
+        match item.as_rule() {
+            Rule::defpublicspec | Rule::defstaticspec => specs.push(build_method_spec(item)),
+            _ => methods.push(build_method(pratt, item)?),
+        }
+    }
+    Ok(TraitDef {
+        name,
+        requires,
+        methods,
+        specs,
+        span,
+    })
+}
+
+fn parse_integer(pair: Pair<Rule>) -> Result<ExprKind, Error> {
+    let radix = match pair.as_rule() {
+        Rule::integer_hexadecimal => 16,
+        Rule::integer_octal => 8,
+        Rule::integer_binary => 2,
+        _ => 10,
+    };
+    let raw = pair.as_str().replace("_", "");
+    if let Ok(integer) = u64::from_str_radix(&raw, radix) {
+        return Ok(ExprKind::Integer(integer));
+    }
+    BigInt::parse_bytes(raw.as_bytes(), radix).map(ExprKind::BigInteger).ok_or(Error::ExpectedNumber)
+}
+
+/// Resolves the escape sequences accepted by `string_escape`.
+fn unescape(raw: &str) -> String {
+    let mut string = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            string.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('a') => string.push('\u{7}'),
+            Some('b') => string.push('\u{8}'),
+            Some('f') => string.push('\u{c}'),
+            Some('n') => string.push('\n'),
+            Some('r') => string.push('\r'),
+            Some('t') => string.push('\t'),
+            Some('v') => string.push('\u{b}'),
+            Some('u') => {
+                let code = chars.by_ref().take(4).collect::<String>();
+                let c = u32::from_str_radix(&code, 16).ok().and_then(std::char::from_u32);
+                string.push(c.unwrap_or('\u{fffd}'));
+            }
+            Some(c) => string.push(c),
+            None => (),
+        }
+    }
+    string
+}
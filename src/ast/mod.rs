@@ -0,0 +1,4 @@
+#[allow(clippy::module_inception)]
+pub mod ast;
+
+pub use self::ast::*;
@@ -3,11 +3,13 @@ extern crate baik;
 extern crate pest_derive;
 extern crate clap;
 
-use baik::core::interpreter;
-use clap::{Arg, App};
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use baik::core::Interpreter;
+use baik::vm::inac;
+use clap::{Arg, App, AppSettings, SubCommand};
+use std::fs::{self, File};
 use std::io::prelude::*;
+use std::path::Path;
+use std::process;
 
 fn main() -> std::io::Result<()>{
 
@@ -15,26 +17,61 @@ fn main() -> std::io::Result<()>{
                     .version("v10.0")
                     .author("Eka Tresna Irawan <anak10thn@gmail.com>")
                     .about("Bahasa Anak Indonesia untuk Komputer")
+                    .setting(AppSettings::SubcommandsNegateReqs)
+                    .setting(AppSettings::ArgsNegateSubcommands)
                     .arg(Arg::with_name("INPUT")
-                        .help("Masukan berkas BAIK (.ina)")
+                        .help("Masukan berkas BAIK (.ina) atau berkas terkompilasi (.inac)")
                         .required(true)
                         .index(1))
+                    .subcommand(SubCommand::with_name("kompilasi")
+                        .about("Mengompilasi berkas .ina menjadi berkas .inac")
+                        .arg(Arg::with_name("INPUT")
+                            .help("Masukan berkas BAIK (.ina)")
+                            .required(true)
+                            .index(1)))
                     .get_matches();
 
+    if let Some(matches) = matches.subcommand_matches("kompilasi") {
+        let filename = matches.value_of("INPUT").unwrap();
+        let contents = read_script(filename)?;
+        match Interpreter::new().compile_inac(&contents) {
+            Ok(bytes) => fs::write(Path::new(filename).with_extension("inac"), bytes)?,
+            Err(e) => exit_with(e),
+        }
+        return Ok(());
+    }
+
     let filename = matches.value_of("INPUT").unwrap();
     let mut f = File::open(filename).expect("Berkas tidak ditemukan!");
-    let mut contents = String::new();
-    f.read_to_string(&mut contents)?;
-    interpreter(contents);
-    // for line in BufReader::new(f).lines() {
-    //     interpreter(line.unwrap());
-    // }
-
-    match matches.occurrences_of("v") {
-        0 => println!("exit"),
-        1 => println!("Some verbose info"),
-        2 => println!("Tons of verbose info"),
-        3 | _ => println!("Don't be crazy"),
+    let mut contents = Vec::new();
+    f.read_to_end(&mut contents)?;
+
+    let mut interpreter = Interpreter::new();
+    let result = if inac::is_inac(&contents) {
+        interpreter.run_inac(&contents)
+    } else {
+        match String::from_utf8(contents) {
+            Ok(script) => interpreter.run(&script),
+            Err(_) => {
+                eprintln!("Kesalahan: berkas bukan teks UTF-8");
+                process::exit(1);
+            }
+        }
+    };
+    if let Err(e) = result {
+        exit_with(e);
     }
     Ok(())
 }
+
+fn read_script(filename: &str) -> std::io::Result<String> {
+    let mut f = File::open(filename).expect("Berkas tidak ditemukan!");
+    let mut contents = String::new();
+    f.read_to_string(&mut contents)?;
+    Ok(contents)
+}
+
+fn exit_with(e: baik::Error) -> ! {
+    eprintln!("Kesalahan: {}", e);
+    process::exit(1);
+}
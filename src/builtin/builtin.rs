@@ -1,11 +1,16 @@
 use crate::*;
-use {Function, Functions, Value, to_value};
+use {Function, Functions, Value};
 use Error;
 use self::core::math::math::Math;
+use std::convert::TryFrom;
+use value::{Decimal, Rounding};
+use num_rational::BigRational;
+use num_traits::Zero;
 
 pub struct BuiltIn {}
 
 impl BuiltIn {
+    #[allow(clippy::new_ret_no_self)]
     pub fn new() -> Functions {
         let mut functions = Functions::new();
         functions.insert("min".to_owned(), create_min_fuction());
@@ -13,6 +18,12 @@ impl BuiltIn {
         functions.insert("panjang".to_owned(), create_len_fuction());
         functions.insert("kosong".to_owned(), create_is_empty_fuction());
         functions.insert("untaian".to_owned(), create_array_function());
+        functions.insert("jenis".to_owned(), create_type_function());
+        functions.insert("Desimal".to_owned(), create_decimal_function());
+        functions.insert("Bita".to_owned(), create_bytes_function());
+        functions.insert("bulatkan".to_owned(), create_round_function());
+        functions.insert("pecahan".to_owned(), create_rational_function());
+        functions.insert("ke_float".to_owned(), create_float_function());
         functions
     }
 }
@@ -45,10 +56,10 @@ fn compare(compare: Compare) -> Function {
                         for value in array {
                             if prev.is_ok() {
                                 if compare == Compare::Min {
-                                    if value.lt(prev.as_ref().unwrap())? == to_value(true) {
+                                    if value.lt(prev.as_ref().unwrap())? == Value::Bool(true) {
                                         prev = Ok(value)
                                     }
-                                } else if value.gt(prev.as_ref().unwrap())? == to_value(true) {
+                                } else if value.gt(prev.as_ref().unwrap())? == Value::Bool(true) {
                                     prev = Ok(value)
                                 }
                             } else {
@@ -59,10 +70,10 @@ fn compare(compare: Compare) -> Function {
                     _ => {
                         if prev.is_ok() {
                             if compare == Compare::Min {
-                                if value.lt(prev.as_ref().unwrap())? == to_value(true) {
+                                if value.lt(prev.as_ref().unwrap())? == Value::Bool(true) {
                                     prev = Ok(value)
                                 }
-                            } else if value.gt(prev.as_ref().unwrap())? == to_value(true) {
+                            } else if value.gt(prev.as_ref().unwrap())? == Value::Bool(true) {
                                 prev = Ok(value)
                             }
                         } else {
@@ -82,11 +93,12 @@ fn create_is_empty_fuction() -> Function {
         max_args: Some(1),
         min_args: Some(1),
         compiled: Box::new(|values| match *values.first().unwrap() {
-            Value::String(ref string) => Ok(to_value(string.is_empty())),
-            Value::Array(ref array) => Ok(to_value(array.is_empty())),
-            Value::Object(ref object) => Ok(to_value(object.is_empty())),
-            Value::Null => Ok(to_value(true)),
-            _ => Ok(to_value(false)),
+            Value::String(ref string) => Ok(Value::from(string.is_empty())),
+            Value::Bytes(ref bytes) => Ok(Value::from(bytes.is_empty())),
+            Value::Array(ref array) => Ok(Value::from(array.is_empty())),
+            Value::Map(ref map) => Ok(Value::from(map.is_empty())),
+            Value::Null => Ok(Value::from(true)),
+            _ => Ok(Value::from(false)),
         }),
     }
 }
@@ -98,13 +110,14 @@ fn create_len_fuction() -> Function {
         compiled: Box::new(|values| {
             let value = values.first().unwrap();
             match *value {
-                Value::String(ref string) => Ok(to_value(string.len())),
-                Value::Array(ref array) => Ok(to_value(array.len())),
-                Value::Object(ref object) => Ok(to_value(object.len())),
-                Value::Null => Ok(to_value(0)),
+                Value::String(ref string) => Ok(Value::from(string.chars().count())),
+                Value::Bytes(ref bytes) => Ok(Value::from(bytes.len())),
+                Value::Array(ref array) => Ok(Value::from(array.len())),
+                Value::Map(ref map) => Ok(Value::from(map.len())),
+                Value::Null => Ok(Value::Integer(0)),
                 _ => {
                     Err(Error::Custom(format!("len() only accept string, array, object and \
-                                               null. But the given is: {:?}",
+                                               null. But the given is: {}",
                                               value)))
                 }
             }
@@ -113,5 +126,107 @@ fn create_len_fuction() -> Function {
 }
 
 fn create_array_function() -> Function {
-    Function::new(|values| Ok(to_value(values)))
+    Function::new(|values| Ok(Value::Array(values)))
+}
+
+fn create_type_function() -> Function {
+    Function {
+        max_args: Some(1),
+        min_args: Some(1),
+        compiled: Box::new(|values| Ok(Value::String(values[0].type_name()))),
+    }
+}
+
+fn create_decimal_function() -> Function {
+    Function {
+        max_args: Some(1),
+        min_args: Some(1),
+        compiled: Box::new(|values| to_decimal(&values[0]).map(Value::Decimal)),
+    }
+}
+
+/// `Bita("teks")` is the UTF-8 bytes of the text, `Bita([104, 105])` the
+/// bytes in the array.
+fn create_bytes_function() -> Function {
+    Function {
+        max_args: Some(1),
+        min_args: Some(1),
+        compiled: Box::new(|values| match values[0] {
+            Value::String(ref text) => Ok(Value::Bytes(text.as_bytes().to_vec())),
+            Value::Bytes(ref bytes) => Ok(Value::Bytes(bytes.clone())),
+            Value::Array(ref items) => {
+                items.iter()
+                    .map(|item| {
+                        item.as_i64()
+                            .and_then(|byte| u8::try_from(byte).ok())
+                            .ok_or_else(|| Error::InvalidByte(item.clone()))
+                    })
+                    .collect::<Result<_, _>>()
+                    .map(Value::Bytes)
+            }
+            _ => Err(Error::ExpectedArray),
+        }),
+    }
+}
+
+/// `bulatkan(x, tempat)` rounds half-even to `tempat` digits after the point,
+/// `bulatkan(x, tempat, :setengah_atas)` rounds half away from zero.
+fn create_round_function() -> Function {
+    Function {
+        max_args: Some(3),
+        min_args: Some(2),
+        compiled: Box::new(|values| {
+            let decimal = to_decimal(&values[0])?;
+            let scale = match values[1] {
+                Value::Integer(scale) => u32::try_from(scale).map_err(|_| Error::ExpectedNumber)?,
+                _ => return Err(Error::ExpectedNumber),
+            };
+            let rounding = match values.get(2) {
+                None => Rounding::HalfEven,
+                Some(Value::Atom(atom)) => Rounding::from_name(atom.name())?,
+                Some(value) => return Err(Error::UnknownRounding(value.to_string())),
+            };
+            Ok(Value::Decimal(decimal.round(scale, rounding)))
+        }),
+    }
+}
+
+/// Decimals and integers as they are, text as it is written and floats as
+/// they are printed, so `Desimal(0.1)` is `0.1d`.
+fn to_decimal(value: &Value) -> Result<Decimal, Error> {
+    match *value {
+        Value::String(ref text) => text.parse(),
+        Value::Float(float) => float.to_string().parse(),
+        ref value => value.as_decimal().ok_or_else(|| Error::InvalidDecimal(value.to_string())),
+    }
+}
+
+/// `pecahan(pembilang, penyebut)` is the fraction in lowest terms, and
+/// `pecahan(x)` an integer or a decimal as one, so `pecahan(0.25d)` is `1/4`.
+fn create_rational_function() -> Function {
+    Function {
+        max_args: Some(2),
+        min_args: Some(1),
+        compiled: Box::new(|values| {
+            let numerator = values[0].as_rational().ok_or(Error::ExpectedNumber)?;
+            let denominator = match values.get(1) {
+                Some(value) => value.as_rational().ok_or(Error::ExpectedNumber)?,
+                None => BigRational::from_integer(1.into()),
+            };
+            if denominator.is_zero() {
+                return Err(Error::DivisionByZero);
+            }
+            Ok(Value::Rational(numerator / denominator))
+        }),
+    }
+}
+
+/// `ke_float(x)` is the nearest float to any number, the only way to turn an
+/// exact one into a float.
+fn create_float_function() -> Function {
+    Function {
+        max_args: Some(1),
+        min_args: Some(1),
+        compiled: Box::new(|values| values[0].as_f64().map(Value::Float).ok_or(Error::ExpectedNumber)),
+    }
 }
@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod builtin;
 
 pub use self::builtin::*;
\ No newline at end of file
@@ -1,31 +1,67 @@
-use pest::Parser;
-use pest::iterators::{Pairs};
-use crate::{eval};
-
-#[derive(Parser)]
-#[grammar = "grammar/baik.pest"]
-struct BaikLexer;
-//use Rule::*;
-
-fn pair(expr : Pairs<Rule>){
-    for inner_pair in expr {
-        match inner_pair.as_rule() {
-            function => {
-                println!("Digit:   {}", inner_pair.as_str());
-                pair(inner_pair.into_inner());
-            },
-            EOI => {
-                println!("EOI:   {}", inner_pair.as_str());
-                pair(inner_pair.into_inner());
-            },
-            _ => {
-                println!("----");
-            }
-        };
+use std::io::Write;
+use crate::*;
+use ast::{self, Program};
+use vm::{self, inac, Module, Vm};
+use Value;
+use Error;
+
+/// Runs BAIK programs by compiling them to bytecode for a `Vm`. Globals, types
+/// and traits stay defined from one run to the next.
+pub struct Interpreter {
+    vm: Vm,
+}
+
+impl Interpreter {
+    pub fn new() -> Interpreter {
+        Interpreter { vm: Vm::new() }
+    }
+
+    /// Creates an interpreter whose `tulis` output goes to `output` instead of stdout.
+    pub fn with_output<W: Write + 'static>(output: W) -> Interpreter {
+        Interpreter { vm: Vm::with_output(output) }
+    }
+
+    /// Parses and runs a script, returning the value of its last expression.
+    pub fn run(&mut self, baik_script: &str) -> Result<Value, Error> {
+        let program = ast::parse(baik_script)?;
+        self.exec(&program)
+    }
+
+    /// Runs an already parsed program, returning the value of its last expression.
+    ///
+    /// Definitions are loaded before any expression runs, so a type can be used
+    /// above the place it is defined.
+    pub fn exec(&mut self, program: &Program) -> Result<Value, Error> {
+        let module = self.compile(program)?;
+        self.vm.run(&module)
+    }
+
+    /// Compiles a program against the globals defined so far.
+    pub fn compile(&mut self, program: &Program) -> Result<Module, Error> {
+        vm::compile(program, self.vm.globals_mut())
+    }
+
+    /// Compiles a script to the contents of an `.inac` file, see `vm::inac`.
+    pub fn compile_inac(&mut self, baik_script: &str) -> Result<Vec<u8>, Error> {
+        let program = ast::parse(baik_script)?;
+        let module = self.compile(&program)?;
+        inac::encode(&module, self.vm.globals_mut())
+    }
+
+    /// Runs a program compiled by `compile_inac`, returning the value of its
+    /// last expression.
+    pub fn run_inac(&mut self, bytes: &[u8]) -> Result<Value, Error> {
+        let module = inac::decode(bytes, self.vm.globals_mut())?;
+        self.vm.run(&module)
     }
 }
 
-pub fn interpreter(baik_script: String) {
-    let pairs = BaikLexer::parse(Rule::input, &baik_script).unwrap_or_else(|e| panic!("{}", e));
-    pair(pairs);
-}
\ No newline at end of file
+impl Default for Interpreter {
+    fn default() -> Interpreter {
+        Interpreter::new()
+    }
+}
+
+pub fn interpreter(baik_script: String) -> Result<Value, Error> {
+    Interpreter::new().run(&baik_script)
+}
@@ -1,14 +1,27 @@
 use crate::*;
-use serde_json::Value;
+use Value;
 use Error;
-use to_value;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_integer::Integer;
+use num_traits::{One, Signed, Zero};
+use value::Decimal;
 
 pub trait Math {
     fn add(&self, value: &Value) -> Result<Value, Error>;
     fn mul(&self, value: &Value) -> Result<Value, Error>;
     fn sub(&self, value: &Value) -> Result<Value, Error>;
     fn div(&self, value: &Value) -> Result<Value, Error>;
+    fn int_div(&self, value: &Value) -> Result<Value, Error>;
     fn rem(&self, value: &Value) -> Result<Value, Error>;
+    fn pow(&self, value: &Value) -> Result<Value, Error>;
+    fn shl(&self, value: &Value) -> Result<Value, Error>;
+    fn shr(&self, value: &Value) -> Result<Value, Error>;
+    fn bitand(&self, value: &Value) -> Result<Value, Error>;
+    fn bitor(&self, value: &Value) -> Result<Value, Error>;
+    fn bitxor(&self, value: &Value) -> Result<Value, Error>;
     fn eq(&self, value: &Value) -> Result<Value, Error>;
     fn ne(&self, value: &Value) -> Result<Value, Error>;
     fn gt(&self, value: &Value) -> Result<Value, Error>;
@@ -17,190 +30,378 @@ pub trait Math {
     fn le(&self, value: &Value) -> Result<Value, Error>;
     fn and(&self, value: &Value) -> Result<Value, Error>;
     fn or(&self, value: &Value) -> Result<Value, Error>;
+    fn neg(&self) -> Result<Value, Error>;
 }
 
+/// The most bits `**` and `<<` give an integer, the digits of a decimal or
+/// either part of a fraction, about 20 million decimal digits. Past it they
+/// fail with `Error::IntegerOverflow` instead of taking all the memory.
+pub const MAX_BITS: u64 = 1 << 26;
+
+/// Integers stay exact: a result that does not fit in an `i64` becomes a
+/// `BilanganBesar`, which turns back into an `i64` once it fits again.
+/// Decimals with decimals or integers stay exact too, and so do fractions
+/// with fractions, decimals or integers. Neither mixes with floats.
 impl Math for Value {
     fn add(&self, value: &Value) -> Result<Value, Error> {
-        if self.is_number() && value.is_number() {
-            if self.is_f64() || value.is_f64() {
-                Ok(to_value(self.get_f64() + value.get_f64()))
-            } else if self.is_i64() || value.is_i64() {
-                Ok(to_value(self.get_i64() + value.get_i64()))
-            } else {
-                Ok(to_value(self.get_u64() + value.get_u64()))
+        match (self, value) {
+            (Value::String(a), Value::String(b)) => Ok(Value::String(a.clone() + b)),
+            (Value::Bytes(a), Value::Bytes(b)) => Ok(Value::Bytes([&a[..], &b[..]].concat())),
+            _ => {
+                arithmetic(self,
+                           value,
+                           i64::checked_add,
+                           |a, b| a + b,
+                           |a, b| Some(a + b),
+                           |a, b| Some(a + b),
+                           |a, b| a + b)
             }
-        } else if self.is_string() && value.is_string() {
-            Ok(to_value(self.get_string() + value.get_str()))
-        } else {
-            Err(Error::UnsupportedTypes(self.format(), value.format()))
         }
     }
 
     fn mul(&self, value: &Value) -> Result<Value, Error> {
-        if self.is_number() && value.is_number() {
-            if self.is_f64() || value.is_f64() {
-                Ok(to_value(self.get_f64() * value.get_f64()))
-            } else if self.is_i64() || value.is_i64() {
-                Ok(to_value(self.get_i64() * value.get_i64()))
-            } else {
-                Ok(to_value(self.get_u64() * value.get_u64()))
-            }
-        } else {
-            Err(Error::UnsupportedTypes(self.format(), value.format()))
-        }
+        arithmetic(self,
+                   value,
+                   i64::checked_mul,
+                   |a, b| a * b,
+                   |a, b| Some(a * b),
+                   |a, b| Some(a * b),
+                   |a, b| a * b)
     }
 
     fn sub(&self, value: &Value) -> Result<Value, Error> {
-        if self.is_number() && value.is_number() {
-            if self.is_f64() || value.is_f64() {
-                Ok(to_value(self.get_f64() - value.get_f64()))
-            } else {
-                Ok(to_value(self.get_i64() - value.get_i64()))
-            }
-        } else {
-            Err(Error::UnsupportedTypes(self.format(), value.format()))
-        }
+        arithmetic(self,
+                   value,
+                   i64::checked_sub,
+                   |a, b| a - b,
+                   |a, b| Some(a - b),
+                   |a, b| Some(a - b),
+                   |a, b| a - b)
     }
 
+    /// Gives a float, a decimal for decimals, see `Decimal::checked_div`, or
+    /// the exact fraction when either is one. `//` is the integer division.
     fn div(&self, value: &Value) -> Result<Value, Error> {
-        if self.is_number() && value.is_number() {
-            Ok(to_value(self.get_f64() / value.get_f64()))
-        } else {
-            Err(Error::UnsupportedTypes(self.format(), value.format()))
+        check_divisor(self, value)?;
+        if has_rational(self, value) {
+            if let (Some(a), Some(b)) = (self.as_rational(), value.as_rational()) {
+                return checked_div_rational(&a, &b).map(Value::Rational).ok_or(Error::DivisionByZero);
+            }
+        }
+        if has_decimal(self, value) {
+            if let (Some(a), Some(b)) = (self.as_decimal(), value.as_decimal()) {
+                return a.checked_div(&b).map(Value::Decimal).ok_or(Error::DivisionByZero);
+            }
         }
+        float_operation(self, value, |a, b| a / b)
+    }
+
+    /// The quotient rounded toward zero, so `a == (a // b) * b + a % b`.
+    fn int_div(&self, value: &Value) -> Result<Value, Error> {
+        check_divisor(self, value)?;
+        arithmetic(self,
+                   value,
+                   i64::checked_div,
+                   |a, b| a / b,
+                   |a, b| checked_div_rational(a, b).map(|quotient| quotient.trunc()),
+                   Decimal::checked_div_trunc,
+                   |a, b| (a / b).trunc())
     }
 
     fn rem(&self, value: &Value) -> Result<Value, Error> {
-        if self.is_number() && value.is_number() {
-            if self.is_f64() || value.is_f64() {
-                Ok(to_value(self.get_f64() % value.get_f64()))
-            } else if self.is_i64() || value.is_i64() {
-                Ok(to_value(self.get_i64() % value.get_i64()))
-            } else {
-                Ok(to_value(self.get_u64() % value.get_u64()))
+        check_divisor(self, value)?;
+        arithmetic(self,
+                   value,
+                   i64::checked_rem,
+                   |a, b| a % b,
+                   |a, b| checked_div_rational(a, b).map(|quotient| a - b * quotient.trunc()),
+                   Decimal::checked_rem,
+                   |a, b| a % b)
+    }
+
+    /// An integer to a power that is not negative stays an integer, a decimal
+    /// to an integer power a decimal and a fraction to one a fraction. Anything
+    /// else gives a float.
+    fn pow(&self, value: &Value) -> Result<Value, Error> {
+        // `0 ** -n` is `1 / 0 ** n`, as it is for the decimals and fractions below.
+        let zero = match self {
+            Value::Integer(a) => *a == 0,
+            Value::Float(a) => *a == 0.0,
+            _ => false,
+        };
+        if zero && value.as_f64().is_some_and(|b| b < 0.0) {
+            return Err(Error::DivisionByZero);
+        }
+        if let (Value::Integer(a), Value::Integer(b)) = (self, value) {
+            if let Some(power) = u32::try_from(*b).ok().and_then(|b| a.checked_pow(b)) {
+                return Ok(Value::Integer(power));
+            }
+        }
+        if let (Value::Decimal(a), Value::Integer(b)) = (self, value) {
+            let power = u32::try_from(b.unsigned_abs())
+                .ok()
+                .and_then(|b| a.checked_pow(b))
+                .ok_or_else(|| Error::IntegerOverflow(format!("{} ** {}", self, value)))?;
+            if *b >= 0 {
+                return Ok(Value::Decimal(power));
+            }
+            let one = Decimal::new(BigInt::one(), 0);
+            return one.checked_div(&power).map(Value::Decimal).ok_or(Error::DivisionByZero);
+        }
+        if let (Value::Rational(a), Value::Integer(b)) = (self, value) {
+            let power = u32::try_from(b.unsigned_abs())
+                .ok()
+                .filter(|&b| power_fits(a.numer(), b) && power_fits(a.denom(), b))
+                .map(|b| BigRational::new_raw(a.numer().pow(b), a.denom().pow(b)))
+                .ok_or_else(|| Error::IntegerOverflow(format!("{} ** {}", self, value)))?;
+            if *b >= 0 {
+                return Ok(Value::Rational(power));
             }
-        } else {
-            Err(Error::UnsupportedTypes(self.format(), value.format()))
+            return checked_div_rational(&BigRational::one(), &power)
+                .map(Value::Rational)
+                .ok_or(Error::DivisionByZero);
+        }
+        match (self.as_bigint(), value.as_bigint()) {
+            (Some(a), Some(b)) if !b.is_negative() => {
+                let power = match u32::try_from(&b) {
+                    Ok(b) if power_fits(&a, b) => Some(a.pow(b)),
+                    // Only these have a power that can be worked out with such an exponent.
+                    Err(_) if a.is_zero() || a.is_one() => Some(a),
+                    Err(_) if a == -BigInt::one() => Some(if b.is_even() { BigInt::one() } else { a }),
+                    _ => None,
+                };
+                power.map(Value::from)
+                    .ok_or_else(|| Error::IntegerOverflow(format!("{} ** {}", self, value)))
+            }
+            _ => float_operation(self, value, f64::powf),
         }
     }
 
+    fn shl(&self, value: &Value) -> Result<Value, Error> {
+        shift(self,
+              value,
+              |a, bits| a.checked_shl(bits).filter(|shifted| shifted >> bits == a),
+              |a, bits| {
+                  if a.is_zero() || a.bits() + u64::from(bits) <= MAX_BITS {
+                      Some(a << bits)
+                  } else {
+                      None
+                  }
+              })
+    }
+
+    fn shr(&self, value: &Value) -> Result<Value, Error> {
+        shift(self, value, |a, bits| Some(a >> bits.min(63)), |a, bits| Some(a >> bits))
+    }
+
+    fn bitand(&self, value: &Value) -> Result<Value, Error> {
+        bitwise(self, value, |a, b| a & b, |a, b| a & b)
+    }
+
+    fn bitor(&self, value: &Value) -> Result<Value, Error> {
+        bitwise(self, value, |a, b| a | b, |a, b| a | b)
+    }
+
+    fn bitxor(&self, value: &Value) -> Result<Value, Error> {
+        bitwise(self, value, |a, b| a ^ b, |a, b| a ^ b)
+    }
+
     fn eq(&self, value: &Value) -> Result<Value, Error> {
-        if self.is_number() && value.is_number() {
-            Ok(to_value(self.get_f64() == value.get_f64()))
-        } else {
-            Ok(to_value(self == value))
-        }
+        Ok(Value::Bool(self == value))
     }
 
     fn ne(&self, value: &Value) -> Result<Value, Error> {
-        if self.is_number() && value.is_number() {
-            Ok(to_value(self.get_f64() != value.get_f64()))
-        } else {
-            Ok(to_value(self != value))
-        }
+        Ok(Value::Bool(self != value))
     }
 
     fn gt(&self, value: &Value) -> Result<Value, Error> {
-        if self.is_null() || value.is_null() {
-            return Ok(to_value(false));
-        }
-        if self.is_number() && value.is_number() {
-            Ok(to_value(self.get_f64() > value.get_f64()))
-        } else {
-            Err(Error::UnsupportedTypes(self.format(), value.format()))
-        }
+        compare(self, value, |ordering| ordering == Ordering::Greater)
     }
 
     fn lt(&self, value: &Value) -> Result<Value, Error> {
-        if self.is_null() || value.is_null() {
-            return Ok(to_value(false));
-        }
-        if self.is_number() && value.is_number() {
-            Ok(to_value(self.get_f64() < value.get_f64()))
-        } else {
-            Err(Error::UnsupportedTypes(self.format(), value.format()))
-        }
+        compare(self, value, |ordering| ordering == Ordering::Less)
     }
 
     fn ge(&self, value: &Value) -> Result<Value, Error> {
-        if self.is_null() || value.is_null() {
-            return Ok(to_value(false));
-        }
-        if self.is_number() && value.is_number() {
-            Ok(to_value(self.get_f64() >= value.get_f64()))
-        } else {
-            Err(Error::UnsupportedTypes(self.format(), value.format()))
-        }
+        compare(self, value, |ordering| ordering != Ordering::Less)
     }
 
     fn le(&self, value: &Value) -> Result<Value, Error> {
-        if self.is_null() || value.is_null() {
-            return Ok(to_value(false));
-        }
-        if self.is_number() && value.is_number() {
-            Ok(to_value(self.get_f64() <= value.get_f64()))
-        } else {
-            Err(Error::UnsupportedTypes(self.format(), value.format()))
-        }
+        compare(self, value, |ordering| ordering != Ordering::Greater)
     }
 
     fn and(&self, value: &Value) -> Result<Value, Error> {
-        if self.is_boolean() && value.is_boolean() {
-            Ok(to_value(self.get_boolean() && value.get_boolean()))
-        } else {
-            Err(Error::UnsupportedTypes(self.format(), value.format()))
+        match (self, value) {
+            (Value::Bool(a), Value::Bool(b)) => Ok(Value::Bool(*a && *b)),
+            _ => Err(unsupported(self, value)),
         }
     }
 
     fn or(&self, value: &Value) -> Result<Value, Error> {
-        if self.is_boolean() && value.is_boolean() {
-            Ok(to_value(self.get_boolean() || value.get_boolean()))
-        } else {
-            Err(Error::UnsupportedTypes(self.format(), value.format()))
+        match (self, value) {
+            (Value::Bool(a), Value::Bool(b)) => Ok(Value::Bool(*a || *b)),
+            _ => Err(unsupported(self, value)),
         }
     }
-}
 
+    /// `-x`, in the type of `x`. `-0.0` stays a negative zero.
+    fn neg(&self) -> Result<Value, Error> {
+        match self {
+            Value::Integer(a) => Ok(match a.checked_neg() {
+                Some(negated) => Value::Integer(negated),
+                None => Value::from(-BigInt::from(*a)),
+            }),
+            Value::BigInteger(a) => Ok(Value::from(-a)),
+            Value::Float(a) => Ok(Value::Float(-a)),
+            Value::Decimal(a) => Ok(Value::Decimal(Decimal::new(-a.digits(), a.scale()))),
+            Value::Rational(a) => Ok(Value::Rational(-a)),
+            _ => Err(Error::ExpectedNumber),
+        }
+    }
+}
 
-trait Type {
-    fn get_f64(&self) -> f64;
-    fn get_string(&self) -> String;
-    fn get_str(&self) -> &str;
-    fn get_u64(&self) -> u64;
-    fn get_i64(&self) -> i64;
-    fn get_boolean(&self) -> bool;
-    fn format(&self) -> String;
+/// Numbers where at least one is a float are worked out as floats. Decimals
+/// and fractions would no longer be exact, so they are not.
+fn float_operation<F>(a: &Value, b: &Value, operation: F) -> Result<Value, Error>
+    where F: Fn(f64, f64) -> f64
+{
+    if has_decimal(a, b) || has_rational(a, b) {
+        return Err(unsupported(a, b));
+    }
+    match (a.as_f64(), b.as_f64()) {
+        (Some(a), Some(b)) => Ok(Value::Float(operation(a, b))),
+        _ => Err(unsupported(a, b)),
+    }
 }
 
-impl Type for Value {
-    fn get_f64(&self) -> f64 {
-        match *self {
-            Value::Number(ref n) => n.as_f64().unwrap(),
-            _ => panic!("not a number"),
+/// Integers are worked out as `i64` while the result fits, and as a
+/// `BilanganBesar` otherwise. A fraction with a fraction, a decimal or an
+/// integer is worked out as fractions, and a decimal with a decimal or an
+/// integer as decimals, where `None` is a division by zero.
+fn arithmetic<C, B, R, D, F>(a: &Value,
+                             b: &Value,
+                             checked: C,
+                             big: B,
+                             rational: R,
+                             decimal: D,
+                             float: F)
+                             -> Result<Value, Error>
+    where C: Fn(i64, i64) -> Option<i64>,
+          B: Fn(BigInt, BigInt) -> BigInt,
+          R: Fn(&BigRational, &BigRational) -> Option<BigRational>,
+          D: Fn(&Decimal, &Decimal) -> Option<Decimal>,
+          F: Fn(f64, f64) -> f64
+{
+    if let (Value::Integer(x), Value::Integer(y)) = (a, b) {
+        if let Some(result) = checked(*x, *y) {
+            return Ok(Value::Integer(result));
         }
     }
-
-    fn get_string(&self) -> String {
-        self.as_str().unwrap().to_owned()
+    if let (Some(x), Some(y)) = (a.as_bigint(), b.as_bigint()) {
+        return Ok(Value::from(big(x, y)));
+    }
+    if has_rational(a, b) {
+        if let (Some(x), Some(y)) = (a.as_rational(), b.as_rational()) {
+            return rational(&x, &y).map(Value::Rational).ok_or(Error::DivisionByZero);
+        }
+    }
+    if has_decimal(a, b) {
+        if let (Some(x), Some(y)) = (a.as_decimal(), b.as_decimal()) {
+            return decimal(&x, &y).map(Value::Decimal).ok_or(Error::DivisionByZero);
+        }
     }
+    float_operation(a, b, float)
+}
 
-    fn get_str(&self) -> &str {
-        self.as_str().unwrap()
+fn has_decimal(a: &Value, b: &Value) -> bool {
+    matches!((a, b), (Value::Decimal(_), _) | (_, Value::Decimal(_)))
+}
+
+fn has_rational(a: &Value, b: &Value) -> bool {
+    matches!((a, b), (Value::Rational(_), _) | (_, Value::Rational(_)))
+}
+
+/// `a / b`, `None` for zero.
+fn checked_div_rational(a: &BigRational, b: &BigRational) -> Option<BigRational> {
+    if b.is_zero() {
+        return None;
     }
+    Some(a / b)
+}
 
-    fn get_u64(&self) -> u64 {
-        self.as_u64().unwrap()
+/// Bitwise operations, which only integers have. A `BilanganBesar` behaves
+/// like an `i64` with as many bits as it needs.
+fn bitwise<I, B>(a: &Value, b: &Value, integer: I, big: B) -> Result<Value, Error>
+    where I: Fn(i64, i64) -> i64,
+          B: Fn(BigInt, BigInt) -> BigInt
+{
+    if let (Value::Integer(x), Value::Integer(y)) = (a, b) {
+        return Ok(Value::Integer(integer(*x, *y)));
     }
+    match (a.as_bigint(), b.as_bigint()) {
+        (Some(x), Some(y)) => Ok(Value::from(big(x, y))),
+        _ => Err(unsupported(a, b)),
+    }
+}
 
-    fn get_i64(&self) -> i64 {
-        self.as_i64().unwrap()
+/// `<<` and `>>` by 0 to `u32::MAX` bits. `<<` moves on to a `BilanganBesar`
+/// when the bits do not fit, up to `MAX_BITS`, `>>` keeps the sign.
+fn shift<C, B>(a: &Value, b: &Value, checked: C, big: B) -> Result<Value, Error>
+    where C: Fn(i64, u32) -> Option<i64>,
+          B: Fn(BigInt, u32) -> Option<BigInt>
+{
+    let bits = match (a.as_bigint(), b.as_bigint()) {
+        (Some(_), Some(bits)) => {
+            u32::try_from(&bits).map_err(|_| Error::InvalidShift(bits.to_string()))?
+        }
+        _ => return Err(unsupported(a, b)),
+    };
+    if let Value::Integer(integer) = a {
+        if let Some(shifted) = checked(*integer, bits) {
+            return Ok(Value::Integer(shifted));
+        }
     }
+    big(a.as_bigint().unwrap(), bits)
+        .map(Value::from)
+        .ok_or_else(|| Error::IntegerOverflow(format!("{} << {}", a, b)))
+}
+
+/// Whether `base ** exponent` has at most `MAX_BITS` bits.
+pub fn power_fits(base: &BigInt, exponent: u32) -> bool {
+    base.bits() <= 1 || base.bits().saturating_mul(u64::from(exponent)) <= MAX_BITS
+}
 
-    fn get_boolean(&self) -> bool {
-        self.as_bool().unwrap()
+/// Zero is checked on the exact value, a tiny fraction or decimal would be
+/// `0.0` as a float.
+fn check_divisor(a: &Value, b: &Value) -> Result<(), Error> {
+    let zero = match *b {
+        Value::Integer(b) => b == 0,
+        Value::BigInteger(ref b) => b.is_zero(),
+        Value::Rational(ref b) => b.is_zero(),
+        Value::Decimal(ref b) => b.is_zero(),
+        Value::Float(b) => b == 0.0,
+        _ => false,
+    };
+    if a.is_number() && zero {
+        return Err(Error::DivisionByZero);
     }
+    Ok(())
+}
 
-    fn format(&self) -> String {
-        format!("{:?}", self)
+/// Numbers compare by exact value, see `Value::compare_numbers`, and
+/// comparing with `nihil` or NaN is always false.
+fn compare<F>(a: &Value, b: &Value, comparison: F) -> Result<Value, Error>
+    where F: Fn(Ordering) -> bool
+{
+    if a.is_null() || b.is_null() {
+        return Ok(Value::Bool(false));
     }
-}
\ No newline at end of file
+    if !a.is_number() || !b.is_number() {
+        return Err(unsupported(a, b));
+    }
+    Ok(Value::Bool(a.compare_numbers(b).is_some_and(comparison)))
+}
+
+fn unsupported(a: &Value, b: &Value) -> Error {
+    Error::UnsupportedTypes(a.to_string(), b.to_string())
+}
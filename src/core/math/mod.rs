@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod math;
 
 pub use self::math::*;
\ No newline at end of file
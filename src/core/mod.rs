@@ -1,7 +1,9 @@
+pub mod object;
 pub mod interpreter;
 pub mod tulis;
 pub mod math;
 
+pub use self::object::*;
 pub use self::interpreter::*;
 pub use self::tulis::*;
 pub use self::math::*;
\ No newline at end of file
@@ -0,0 +1,100 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::rc::Rc;
+use crate::*;
+use ast::MethodKind;
+use vm::chunk::{FunctionProto, Method, Spec, TraitProto, TypeProto};
+use vm::vm::MethodContext;
+use Value;
+
+/// A compiled `fungsi`, named or not, together with the variables it captured,
+/// in the order of `FunctionProto::captures`, and the method it was created
+/// by. Each variable is a cell shared with the frame it comes from, so the
+/// function sees later changes to it.
+pub struct Closure {
+    pub function: Rc<FunctionProto>,
+    pub upvalues: Vec<Rc<RefCell<Value>>>,
+    pub context: Rc<MethodContext>,
+}
+
+impl Closure {
+    /// The name used in error messages and when the function is printed.
+    pub fn name(&self) -> &str {
+        self.function.name.as_deref().unwrap_or("anonim")
+    }
+}
+
+impl fmt::Debug for Closure {
+    // Captured variables can hold the closure itself.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Closure({})", self.name())
+    }
+}
+
+/// A `trait` definition loaded into the interpreter.
+#[derive(Debug)]
+pub struct Trait {
+    pub def: TraitProto,
+}
+
+/// A `type` definition loaded into the interpreter, with the traits its `impl`
+/// blocks were checked against.
+pub struct Type {
+    pub def: TypeProto,
+    pub traits: Vec<Rc<Trait>>,
+}
+
+impl Type {
+    /// All methods called `name`: the type's own first, then the ones in its
+    /// `impl` blocks, then the default methods of its traits.
+    pub fn methods<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Method> + 'a {
+        let impls = self.def.impls.iter().flat_map(|impldef| &impldef.methods);
+        let defaults = self.traits.iter().flat_map(|t| &t.def.methods);
+        self.def
+            .methods
+            .iter()
+            .chain(impls)
+            .chain(defaults)
+            .filter(move |method| method.name == name)
+    }
+
+    /// The specs in the type's traits that `method` implements.
+    pub fn specs<'a>(&'a self,
+                     method: &'a Method)
+                     -> impl Iterator<Item = (&'a Trait, &'a Spec)> + 'a {
+        let is_static = method.kind == MethodKind::Static;
+        self.traits
+            .iter()
+            .flat_map(|tr| tr.def.specs.iter().map(move |spec| (&**tr, spec)))
+            .filter(move |&(_, spec)| {
+                spec.name == method.name && spec.arity == method.proto.arity &&
+                (spec.kind == MethodKind::Static) == is_static
+            })
+    }
+
+    pub fn implements(&self, trait_name: &str) -> bool {
+        self.traits.iter().any(|tr| tr.def.name == trait_name)
+    }
+}
+
+impl fmt::Debug for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Type({})", self.def.name)
+    }
+}
+
+/// A value built with a constructor like `Titik{ x: 1, y: 2 }`. It is shared, so
+/// `@{}` in a method is seen by every holder of the instance.
+#[derive(Debug)]
+pub struct Instance {
+    pub ty: Rc<Type>,
+    pub fields: BTreeMap<String, Value>,
+}
+
+impl Instance {
+    /// Fields in the order the type declares them.
+    pub fn ordered_fields(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.ty.def.props.iter().filter_map(move |prop| self.fields.get_key_value(prop))
+    }
+}
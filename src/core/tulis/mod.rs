@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod tulis;
 
 pub use self::tulis::*;
\ No newline at end of file
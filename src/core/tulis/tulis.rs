@@ -1,3 +1,108 @@
-pub fn tulis(){
-    println!("hello");
-}
\ No newline at end of file
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+use crate::*;
+use self::core::object::Instance;
+use value::{Key, Value};
+
+/// Writes the values one after another, without separators or a trailing newline.
+pub fn tulis<W: Write + ?Sized>(output: &mut W, values: &[Value]) -> io::Result<()> {
+    for value in values {
+        output.write_all(format_value(value).as_bytes())?;
+    }
+    output.flush()
+}
+
+/// Human readable form of a value. Strings, decimals and fractions are written as
+/// they are, so `12.50d` as `12.50` and `pecahan(1, 3)` as `1/3`, everything else
+/// the way it would be written in a script.
+pub fn format_value(value: &Value) -> String {
+    match *value {
+        Value::String(ref string) => string.clone(),
+        Value::Decimal(ref decimal) => decimal.to_string(),
+        Value::Rational(ref rational) => rational.to_string(),
+        _ => format_nested(value),
+    }
+}
+
+/// A value the way it would be written in a script, strings included. An
+/// instance inside itself is written as `<siklus>`.
+pub fn format_nested(value: &Value) -> String {
+    format_inside(value, &mut Vec::new())
+}
+
+/// `outer` holds the instances `value` is written inside of.
+fn format_inside(value: &Value, outer: &mut Vec<*const RefCell<Instance>>) -> String {
+    match *value {
+        Value::Null => "nihil".to_owned(),
+        Value::Bool(true) => "benar".to_owned(),
+        Value::Bool(false) => "salah".to_owned(),
+        Value::Integer(integer) => integer.to_string(),
+        Value::BigInteger(ref integer) => integer.to_string(),
+        Value::Float(float) => format_float(float),
+        Value::Decimal(ref decimal) => format!("{}d", decimal),
+        Value::Rational(ref rational) => {
+            format!("pecahan({}, {})", rational.numer(), rational.denom())
+        }
+        Value::String(ref string) => serde_json::to_string(string).unwrap(),
+        Value::Bytes(ref bytes) => {
+            let items = bytes.iter().map(u8::to_string).collect::<Vec<_>>();
+            format!("Bita([{}])", items.join(", "))
+        }
+        Value::Atom(ref atom) => format!(":{}", atom.name()),
+        Value::Array(ref array) => {
+            let items = array.iter().map(|item| format_inside(item, outer)).collect::<Vec<_>>();
+            format!("[{}]", items.join(", "))
+        }
+        Value::Map(ref map) => {
+            let items = map.iter()
+                .map(|(key, value)| match *key {
+                    Key::String(ref key) if is_keyword(key) => {
+                        format!("{}: {}", key, format_inside(value, outer))
+                    }
+                    ref key => {
+                        format!("{} => {}", format_nested(&key.to_value()), format_inside(value, outer))
+                    }
+                })
+                .collect::<Vec<_>>();
+            format!("{{{}}}", items.join(", "))
+        }
+        Value::Function(ref closure) => format!("<fungsi {}>", closure.name()),
+        Value::Type(ref ty) => ty.def.name.clone(),
+        Value::Instance(ref instance) if outer.contains(&Rc::as_ptr(instance)) => "<siklus>".to_owned(),
+        Value::Instance(ref instance) => {
+            outer.push(Rc::as_ptr(instance));
+            let instance = instance.borrow();
+            let fields = instance.ordered_fields()
+                .map(|(key, value)| format!("{}: {}", key, format_inside(value, outer)))
+                .collect::<Vec<_>>();
+            outer.pop();
+            format!("{}{{{}}}", instance.ty.def.name, fields.join(", "))
+        }
+    }
+}
+
+/// Keys that can be written as `kunci:` in a map literal.
+fn is_keyword(key: &str) -> bool {
+    let mut chars = key.chars();
+    match chars.next() {
+        Some(first) if first.is_lowercase() => chars.all(|c| c.is_alphabetic() || c == '_'),
+        _ => false,
+    }
+}
+
+/// Floats keep 15 significant digits, which hides binary rounding noise like
+/// `0.1 + 0.2`, and always show a fraction so they are not mistaken for integers.
+fn format_float(float: f64) -> String {
+    if !float.is_finite() {
+        return float.to_string();
+    }
+
+    let rounded = format!("{:.14e}", float).parse::<f64>().unwrap_or(float);
+    let text = rounded.to_string();
+    if text.contains('.') || text.contains('e') {
+        text
+    } else {
+        text + ".0"
+    }
+}
@@ -45,10 +45,9 @@ impl Expr {
     }
 
     pub fn exec(&self) -> Result<Value, Error> {
-        if self.compiled.is_none() {
-            Tree::new(self.expression.clone()).compile()?(&self.contexts, &self.functions)
-        } else {
-            self.compiled.as_ref().unwrap()(&self.contexts, &self.functions)
+        match self.compiled {
+            Some(ref compiled) => compiled(&self.contexts, &self.functions),
+            None => Tree::new(self.expression.clone()).compile()?(&self.contexts, &self.functions),
         }
     }
 
@@ -87,18 +86,18 @@ pub struct ExecOptions<'a> {
 impl<'a> ExecOptions<'a> {
     pub fn new(expr: &'a Expr) -> ExecOptions<'a> {
         ExecOptions {
-            expr: expr,
+            expr,
             contexts: None,
             functions: None,
         }
     }
 
-    pub fn contexts(&mut self, contexts: &'a [Context]) -> &'a mut ExecOptions {
+    pub fn contexts(&mut self, contexts: &'a [Context]) -> &'a mut ExecOptions<'_> {
         self.contexts = Some(contexts);
         self
     }
 
-    pub fn functions(&mut self, functions: &'a Functions) -> &'a mut ExecOptions {
+    pub fn functions(&mut self, functions: &'a Functions) -> &'a mut ExecOptions<'_> {
         self.functions = Some(functions);
         self
     }
@@ -107,30 +106,17 @@ impl<'a> ExecOptions<'a> {
         let empty_contexts = create_empty_contexts();
         let empty_functions = Functions::new();
 
-        let contexts = if self.contexts.is_some() {
-            self.contexts.unwrap()
-        } else {
-            &empty_contexts
-        };
-
-        let functions = if self.functions.is_some() {
-            self.functions.unwrap()
-        } else {
-            &empty_functions
-        };
-
-        let compiled = self.expr.get_compiled();
-        if compiled.is_none() {
-            Tree::new(self.expr.expression.clone()).compile()?(contexts, functions)
-        } else {
-            compiled.unwrap()(contexts, functions)
+        let contexts = self.contexts.unwrap_or(&empty_contexts);
+        let functions = self.functions.unwrap_or(&empty_functions);
+
+        match self.expr.get_compiled() {
+            Some(compiled) => compiled(contexts, functions),
+            None => Tree::new(self.expr.expression.clone()).compile()?(contexts, functions),
         }
     }
 }
 
 
 fn create_empty_contexts() -> Contexts {
-    let mut contexts = Contexts::new();
-    contexts.push(Context::new());
-    contexts
+    vec![Context::new()]
 }
\ No newline at end of file
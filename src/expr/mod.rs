@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod expr;
 
 pub use self::expr::*;
\ No newline at end of file
@@ -1,12 +1,12 @@
 use crate::*;
 use std::fmt;
-use serde_json::Value;
+use Value;
 use Error;
 
 pub struct Function {
     pub max_args: Option<usize>,
     pub min_args: Option<usize>,
-    pub compiled: Box<Fn(Vec<Value>) -> Result<Value, Error> + Sync + Send>,
+    pub compiled: Box<dyn Fn(Vec<Value>) -> Result<Value, Error> + Sync + Send>,
 }
 
 impl Function {
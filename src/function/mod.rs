@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod function;
 
 pub use self::function::*;
\ No newline at end of file
@@ -1,17 +1,27 @@
-#![recursion_limit="100"]
+#![recursion_limit="256"]
 extern crate pest;
 
 #[macro_use]
 extern crate pest_derive;
 extern crate serde;
 extern crate serde_json;
+extern crate num_bigint;
+extern crate num_integer;
+extern crate num_rational;
+extern crate num_traits;
+extern crate stacker;
 #[macro_use(quick_error)] extern crate quick_error;
 
+pub mod ast;
 pub mod core;
+pub mod parser;
 pub mod function;
 pub mod tree;
+pub mod value;
+pub mod vm;
 mod operator;
 mod node;
+mod optimizer;
 mod expr;
 mod builtin;
 
@@ -19,16 +29,18 @@ use std::collections::HashMap;
 use serde_json::to_value as json_to_value;
 use serde::Serialize;
 
-pub use serde_json::Value;
+pub use value::{Atom, Decimal, Key, Rounding, Value};
+pub use num_bigint::BigInt;
+pub use num_rational::BigRational;
 
 // from baik
 pub use expr::ExecOptions;
 pub use function::Function;
 pub use expr::Expr;
-use operator::Operator;
 
+/// Converts anything serde can serialize into a `Value`, through its JSON form.
 pub fn to_value<S: Serialize>(v: S) -> Value {
-    json_to_value(v).unwrap()
+    Value::from(json_to_value(v).unwrap())
 }
 
 pub type Context = HashMap<String, Value>;
@@ -39,44 +51,12 @@ pub fn eval(expr: &str) -> Result<Value, Error> {
     Expr::new(expr).compile()?.exec()
 }
 
-pub type Compiled = Box<Fn(&[Context], &Functions) -> Result<Value, Error>>;
+pub type Compiled = Box<dyn Fn(&[Context], &Functions) -> Result<Value, Error>>;
 
 quick_error! {
     /// Expression parsing error
     #[derive(Debug, PartialEq)]
     pub enum Error {
-        /// Unsupported operator yet.
-        UnsupportedOperator(operator: String) {
-            display("Unsupported operator: {:?}", operator)
-        }
-        /// This operator does not support execution.
-        CanNotExec(operator: Operator) {
-            display("This operator does not support execution: {:?}", operator)
-        }
-        /// Your expression may start with non-value operator like ( + * )
-        StartWithNonValueOperator {
-            display("Your expression may start with non-value operator like ( + * ).")
-        }
-        /// Unpaired brackets, left brackets count does not equal right brackets count
-        UnpairedBrackets {
-            display("Unpaired brackets, left brackets count does not equal right brackets count.")
-        }
-        /// Duplicate values node, you may have (2 3) but there is no operators between them
-        DuplicateValueNode {
-            display("Duplicate values node, you may have (2 3) but there is no operators between them.")
-        }
-        /// Duplicate operators node, you may have (+ +) but there is no values between them
-        DuplicateOperatorNode {
-            display("Duplicate operators node, you may have (+ +) but there is no values between them.")
-        }
-        /// You have a comma(,) , but there is no function in front of it.
-        CommaNotWithFunction {
-            display("You have a comma(,) , but there is no function in front of it.")
-        }
-        /// You have empty brackets () , but there is no function in front of it.
-        BracketNotWithFunction {
-            display("You have empty brackets () , but there is no function in front of it.")
-        }
         /// Function not exists.
         FunctionNotExists(ident: String) {
             display("Function not exists: {}", ident)
@@ -101,10 +81,6 @@ quick_error! {
         ExpectedNumber {
             display("Expected number.")
         }
-        /// Failed to parse, no final expression.
-        NoFinalNode {
-            display("Failed to parse, no final expression.")
-        }
         /// The number of arguments is greater than the maximum limit.
         ArgumentsGreater(max: usize) {
             display("The number of arguments is greater than the maximum limit: {}", max)
@@ -121,9 +97,161 @@ quick_error! {
         InvalidRange(ident: String) {
             display("Invalid range expression: {}", ident)
         }
-        /// Can not add child node.
-        CanNotAddChild {
-            display("Can not add child node.")
+        /// An integer result too large to work out, like `2 ** 5000000000`.
+        IntegerOverflow(operation: String) {
+            display("Integer overflow in {}", operation)
+        }
+        /// `/`, `//` or `%` by zero.
+        DivisionByZero {
+            display("Division by zero")
+        }
+        /// Text that `Desimal` can not read as a decimal.
+        InvalidDecimal(text: String) {
+            display("Not a decimal number: {}", text)
+        }
+        /// An item of the array given to `Bita` that is not a byte.
+        InvalidByte(value: Value) {
+            display("Not a byte from 0 to 255: {}", value)
+        }
+        /// A number `Value::to_json` can not write exactly.
+        UnexpectedNumber(number: String) {
+            display("This number has no exact JSON form: {}", number)
+        }
+        /// A rounding mode `bulatkan` does not know.
+        UnknownRounding(mode: String) {
+            display("Unknown rounding mode: {}, expected :setengah_genap or :setengah_atas", mode)
+        }
+        /// `<<` or `>>` by a negative number of bits, or by more than fit in a `u32`.
+        InvalidShift(bits: String) {
+            display("Can not shift by {} bits, the amount has to be from 0 to {}", bits, u32::MAX)
+        }
+        /// Calls nested deeper than the VM allows, usually a recursion that does not end.
+        CallTooDeep(limit: usize) {
+            display("Calls are nested deeper than {}", limit)
+        }
+        /// No clause of the function accepts the given number of arguments.
+        WrongArity(ident: String, given: usize, accepted: String) {
+            display("Function {} can not be called with {} argument(s), it accepts: {}", ident, given, accepted)
+        }
+        /// This value can not be iterated by `untuk`.
+        NotIterable(value: Value) {
+            display("This value can not be iterated: {}", value)
+        }
+        /// `kembali` used outside of a function or method body.
+        ReturnOutsideFunction(line: usize) {
+            display("`kembali` can only be used inside a function or method, line {}", line)
+        }
+        /// `berhenti` used outside of a loop.
+        BreakOutsideLoop(line: usize) {
+            display("`berhenti` can only be used inside a loop, line {}", line)
+        }
+        /// `lanjut` used outside of a loop.
+        ContinueOutsideLoop(line: usize) {
+            display("`lanjut` can only be used inside a loop, line {}", line)
+        }
+        /// This value is not a function and can not be called.
+        NotCallable(value: Value) {
+            display("This value can not be called: {}", value)
+        }
+        /// A function was used where plain data is needed.
+        UnexpectedFunction(ident: String) {
+            display("Function {} can not be used as a value here", ident)
+        }
+        /// No `type` with this name has been defined.
+        TypeNotExists(ident: String) {
+            display("Type not exists: {}", ident)
+        }
+        /// The type does not declare this property.
+        UnknownProperty(typename: String, property: String) {
+            display("Type {} has no property: {}", typename, property)
+        }
+        /// A constructor did not give a value for this property.
+        MissingProperty(typename: String, property: String) {
+            display("Type {} needs a value for property: {}", typename, property)
+        }
+        /// `@` used where there is no receiver.
+        PropertyOutsideMethod(property: String) {
+            display("@{} can only be used inside an instance method", property)
+        }
+        /// The type has no method with this name.
+        MethodNotExists(typename: String, method: String) {
+            display("Type {} has no method: {}", typename, method)
+        }
+        /// A `defp` method called from outside the methods of its type.
+        PrivateMethod(typename: String, method: String) {
+            display("Method {}.{} is private, it can only be called from methods of {}", typename, method, typename)
+        }
+        /// A `defs` method called on an instance.
+        StaticMethodOnInstance(typename: String, method: String) {
+            display("Method {}.{} is static, call it on the type instead of an instance", typename, method)
+        }
+        /// An instance method called on the type itself.
+        InstanceMethodOnType(typename: String, method: String) {
+            display("Method {}.{} needs an instance, it can not be called on the type", typename, method)
+        }
+        /// An atom was used where plain data is needed.
+        UnexpectedAtom(ident: String) {
+            display("Atom :{} can not be used as a value here", ident)
+        }
+        /// A map key that has no JSON form, such as an integer.
+        UnexpectedKey(key: String) {
+            display("Map key {} can not be used as a JSON key", key)
+        }
+        /// An instance that holds itself, which JSON can not write.
+        CyclicValue(ident: String) {
+            display("Instance of {} contains itself and has no JSON form", ident)
+        }
+        /// A type was used where plain data is needed.
+        UnexpectedType(ident: String) {
+            display("Type {} can not be used as a value here", ident)
+        }
+        /// No `trait` with this name has been defined.
+        TraitNotExists(ident: String) {
+            display("Trait not exists: {}", ident)
+        }
+        /// An `impl` block leaves out a method its trait specifies.
+        MissingTraitMethod(typename: String, trait_name: String, method: String) {
+            display("Type {} implements {} but does not define method: {}", typename, trait_name, method)
+        }
+        /// An `impl` block defines a specified method with the wrong number of arguments.
+        TraitMethodArity(typename: String, trait_name: String, method: String, expected: usize, given: usize) {
+            display("Method {}.{} of trait {} must take {} argument(s), not {}", typename, method, trait_name, expected, given)
+        }
+        /// A trait requires another trait the type does not implement.
+        MissingRequiredTrait(typename: String, trait_name: String, required: String) {
+            display("Trait {} requires {}, which type {} does not implement", trait_name, required, typename)
+        }
+        /// A method returned a value that does not match the return type in its trait spec.
+        TraitReturnType(trait_name: String, method: String, expected: String, actual: String) {
+            display("Method {} of trait {} must return {}, found: {}", method, trait_name, expected, actual)
+        }
+        /// An index past the end of an array or string.
+        IndexOutOfRange(index: i64, len: usize) {
+            display("Index {} is out of range for length {}", index, len)
+        }
+        /// This value has no items to index or fields to access.
+        NotIndexable(typename: String) {
+            display("Values of type {} can not be indexed", typename)
+        }
+        /// Variable not exists.
+        VariableNotExists(ident: String) {
+            display("Variable not exists: {}", ident)
+        }
+        /// This expression can not be evaluated yet.
+        UnsupportedExpression(rule: String) {
+            display("Unsupported expression: {}", rule)
+        }
+        /// A compiled `.inac` program is damaged or not one at all.
+        InvalidBytecode(detail: String) {
+            display("Invalid compiled program: {}", detail)
+        }
+        /// A compiled `.inac` program was written in another format version.
+        BytecodeVersion(found: u16, expected: u16) {
+            display("Compiled program has format version {}, but this version of BAIK reads version {}; compile the script again", found, expected)
+        }
+        /// The script does not match the BAIK grammar.
+        Syntax(detail: String) {
+            display("Syntax error: {}", detail)
         }
         /// Custom error.
         Custom(detail: String) {
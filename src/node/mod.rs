@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod node;
 
 pub use self::node::*;
\ No newline at end of file
@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod operator;
 
 pub use self::operator::*;
\ No newline at end of file
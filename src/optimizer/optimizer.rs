@@ -0,0 +1,87 @@
+use crate::*;
+use builtin::BuiltIn;
+use node::Node;
+use operator::Operator;
+use ast::UnaryOp;
+use vm::{apply_operator, apply_unary, check_arguments, map_from_pairs, range_array};
+use Functions;
+use Value;
+
+/// Builtins whose result depends on nothing but their arguments.
+const PURE_BUILTINS: [&str; 11] = ["min",
+                                   "max",
+                                   "panjang",
+                                   "kosong",
+                                   "untaian",
+                                   "jenis",
+                                   "Desimal",
+                                   "Bita",
+                                   "bulatkan",
+                                   "pecahan",
+                                   "ke_float"];
+
+/// Simplifies a tree made by `Tree::parse_node` before it is compiled.
+///
+/// Operators, arrays, maps, ranges and pure builtins whose operands are all
+/// values are worked out through `Math` and the builtin itself. Anything that
+/// fails is left as it is, so the error still comes from `exec`.
+pub fn optimize(node: Node) -> Node {
+    optimize_node(node, &BuiltIn::new())
+}
+
+fn optimize_node(mut node: Node, builtin: &Functions) -> Node {
+    node.children = node.children.into_iter().map(|child| optimize_node(child, builtin)).collect();
+    let values = match values(&node) {
+        Some(values) => values,
+        None => return node,
+    };
+
+    let folded = match node.operator {
+        Operator::Binary(op) if values.len() == 2 => {
+            let mut values = values.into_iter();
+            apply_operator(values.next().unwrap(), op, values.next().unwrap()).ok()
+        }
+        Operator::Unary(UnaryOp::Not) => {
+            match values[0] {
+                Value::Bool(boolean) => Some(Value::Bool(!boolean)),
+                Value::Null => Some(Value::Bool(true)),
+                _ => None,
+            }
+        }
+        Operator::Unary(op) => apply_unary(op, values[0].clone()).ok(),
+        Operator::Range => range_array(&values[0], &values[1]).ok(),
+        Operator::Array => Some(Value::Array(values)),
+        Operator::Map => map_from_pairs(values).ok(),
+        Operator::Function(ref name) if PURE_BUILTINS.contains(&name.as_str()) => {
+            if let Some(result) = call_builtin(builtin, name, values) {
+                // Kept apart from a plain value, because a function given to
+                // `exec` can still replace the builtin.
+                node.operator = Operator::Folded(name.clone(), result);
+            }
+            None
+        }
+        _ => None,
+    };
+
+    match folded {
+        Some(value) => Operator::Value(value).to_node(),
+        None => node,
+    }
+}
+
+/// The values of the children when all of them are values.
+fn values(node: &Node) -> Option<Vec<Value>> {
+    node.children
+        .iter()
+        .map(|child| match child.operator {
+            Operator::Value(ref value) => Some(value.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn call_builtin(builtin: &Functions, name: &str, args: Vec<Value>) -> Option<Value> {
+    let function = builtin.get(name)?;
+    check_arguments(args.len(), function.min_args, function.max_args).ok()?;
+    (function.compiled)(args).ok()
+}
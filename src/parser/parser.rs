@@ -0,0 +1,3 @@
+#[derive(Parser)]
+#[grammar = "grammar/baik.pest"]
+pub struct BaikLexer;
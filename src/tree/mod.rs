@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod tree;
 
 pub use self::tree::*;
\ No newline at end of file
@@ -0,0 +1,296 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul, Sub};
+use std::str::FromStr;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_integer::Integer;
+use num_traits::{One, Signed, Zero};
+use crate::*;
+use self::core::math::power_fits;
+use Error;
+
+/// Digits after the point `/` keeps when the quotient does not end sooner.
+pub const DIVISION_SCALE: u32 = 28;
+
+/// How `Decimal::round` settles a value exactly halfway between two others.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rounding {
+    /// To the even one, so `0.125` becomes `0.12` and `0.135` becomes `0.14`.
+    HalfEven,
+    /// Away from zero, so `0.125` becomes `0.13` and `-0.125` becomes `-0.13`.
+    HalfUp,
+}
+
+impl Rounding {
+    /// The mode an atom given to `bulatkan` names.
+    pub fn from_name(name: &str) -> Result<Rounding, Error> {
+        match name {
+            "setengah_genap" => Ok(Rounding::HalfEven),
+            "setengah_atas" => Ok(Rounding::HalfUp),
+            _ => Err(Error::UnknownRounding(name.to_owned())),
+        }
+    }
+}
+
+/// An exact decimal number like `12.50d`: `digits` divided by 10 to the power
+/// of `scale`. The scale is part of the value, so `12.5d == 12.50d` but they
+/// are written differently.
+#[derive(Debug, Clone)]
+pub struct Decimal {
+    digits: BigInt,
+    scale: u32,
+}
+
+impl Decimal {
+    pub fn new(digits: BigInt, scale: u32) -> Decimal {
+        Decimal { digits, scale }
+    }
+
+    pub fn digits(&self) -> &BigInt {
+        &self.digits
+    }
+
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_zero()
+    }
+
+    /// The exact value of a float, every binary digit of it. `None` for NaN
+    /// and the infinities.
+    pub fn from_f64_exact(float: f64) -> Option<Decimal> {
+        if !float.is_finite() {
+            return None;
+        }
+        let bits = float.to_bits();
+        let biased = ((bits >> 52) & 0x7ff) as i64;
+        let fraction = bits & 0xf_ffff_ffff_ffff;
+        let (mantissa, exponent) = match biased {
+            0 => (fraction, -1074),
+            _ => (fraction | 1 << 52, biased - 1075),
+        };
+        let mantissa = if bits >> 63 == 1 { -BigInt::from(mantissa) } else { BigInt::from(mantissa) };
+        if exponent >= 0 {
+            Some(Decimal::new(mantissa << exponent as usize, 0))
+        } else {
+            // m / 2^n is m * 5^n / 10^n.
+            let scale = -exponent as u32;
+            Some(Decimal::new(mantissa * BigInt::from(5).pow(scale), scale))
+        }
+    }
+
+    /// The exact value of a fraction, when its digits after the point come to
+    /// an end. `None` for ones like `1/3`.
+    pub fn from_rational_exact(rational: &BigRational) -> Option<Decimal> {
+        // Only a denominator made of 2s and 5s divides a power of ten.
+        let mut rest = rational.denom().clone();
+        let mut scale = 0;
+        while !rest.is_one() {
+            let two = (&rest % 2u32).is_zero();
+            let five = (&rest % 5u32).is_zero();
+            if !two && !five {
+                return None;
+            }
+            if two {
+                rest /= 2u32;
+            }
+            if five {
+                rest /= 5u32;
+            }
+            scale += 1;
+        }
+        Some(Decimal::new(rational.numer() * ten_pow(scale) / rational.denom(), scale))
+    }
+
+    /// The nearest float.
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap()
+    }
+
+    /// Rounded to `scale` digits after the point, or padded with zeros to it.
+    pub fn round(&self, scale: u32, rounding: Rounding) -> Decimal {
+        if scale >= self.scale {
+            return Decimal::new(self.rescaled(scale), scale);
+        }
+        let digits = divide_rounded(&self.digits, &ten_pow(self.scale - scale), rounding);
+        Decimal::new(digits, scale)
+    }
+
+    /// `self / other`, with as few digits after the point as it takes but no
+    /// fewer than either operand has. A quotient that does not end within
+    /// `DIVISION_SCALE` digits is rounded half-even there. `None` for zero.
+    pub fn checked_div(&self, other: &Decimal) -> Option<Decimal> {
+        if other.is_zero() {
+            return None;
+        }
+        let min_scale = self.scale.max(other.scale);
+        let scale = min_scale.max(DIVISION_SCALE);
+        // a / b at `scale` digits is A * 10^(scale + sb - sa) / B.
+        let numerator = &self.digits * ten_pow(scale + other.scale - self.scale);
+        let (quotient, remainder) = numerator.div_rem(&other.digits);
+        if remainder.is_zero() {
+            Some(Decimal::new(quotient, scale).trimmed(min_scale))
+        } else {
+            Some(Decimal::new(divide_rounded(&numerator, &other.digits, Rounding::HalfEven), scale))
+        }
+    }
+
+    /// The quotient rounded toward zero, as a whole number. `None` for zero.
+    pub fn checked_div_trunc(&self, other: &Decimal) -> Option<Decimal> {
+        if other.is_zero() {
+            return None;
+        }
+        let scale = self.scale.max(other.scale);
+        Some(Decimal::new(self.rescaled(scale) / other.rescaled(scale), 0))
+    }
+
+    /// What is left after `checked_div_trunc`, with the sign of `self`.
+    /// `None` for zero.
+    pub fn checked_rem(&self, other: &Decimal) -> Option<Decimal> {
+        if other.is_zero() {
+            return None;
+        }
+        let scale = self.scale.max(other.scale);
+        Some(Decimal::new(self.rescaled(scale) % other.rescaled(scale), scale))
+    }
+
+    /// `self` to a whole power, which has `exponent` times the digits after
+    /// the point. `None` if that many do not fit in a `u32`, or the digits
+    /// would pass `MAX_BITS`.
+    pub fn checked_pow(&self, exponent: u32) -> Option<Decimal> {
+        if !power_fits(&self.digits, exponent) {
+            return None;
+        }
+        let scale = self.scale.checked_mul(exponent)?;
+        Some(Decimal::new(self.digits.pow(exponent), scale))
+    }
+
+    /// The digits at a scale at least as large as this one's.
+    fn rescaled(&self, scale: u32) -> BigInt {
+        &self.digits * ten_pow(scale - self.scale)
+    }
+
+    /// Without the zeros at the end, down to `scale` digits after the point.
+    fn trimmed(mut self, scale: u32) -> Decimal {
+        let ten = BigInt::from(10);
+        while self.scale > scale && (&self.digits % &ten).is_zero() {
+            self.digits /= &ten;
+            self.scale -= 1;
+        }
+        self
+    }
+}
+
+fn ten_pow(exponent: u32) -> BigInt {
+    BigInt::from(10).pow(exponent)
+}
+
+/// `numerator / denominator` rounded to a whole number.
+fn divide_rounded(numerator: &BigInt, denominator: &BigInt, rounding: Rounding) -> BigInt {
+    let (quotient, remainder) = numerator.div_rem(denominator);
+    let away_from_zero = match (remainder.abs() * 2u32).cmp(&denominator.abs()) {
+        Ordering::Less => false,
+        Ordering::Greater => true,
+        Ordering::Equal => rounding == Rounding::HalfUp || quotient.is_odd(),
+    };
+    match (away_from_zero, numerator.is_negative() != denominator.is_negative()) {
+        (false, _) => quotient,
+        (true, false) => quotient + BigInt::one(),
+        (true, true) => quotient - BigInt::one(),
+    }
+}
+
+impl Add for &Decimal {
+    type Output = Decimal;
+
+    fn add(self, other: &Decimal) -> Decimal {
+        let scale = self.scale.max(other.scale);
+        Decimal::new(self.rescaled(scale) + other.rescaled(scale), scale)
+    }
+}
+
+impl Sub for &Decimal {
+    type Output = Decimal;
+
+    fn sub(self, other: &Decimal) -> Decimal {
+        let scale = self.scale.max(other.scale);
+        Decimal::new(self.rescaled(scale) - other.rescaled(scale), scale)
+    }
+}
+
+impl Mul for &Decimal {
+    type Output = Decimal;
+
+    fn mul(self, other: &Decimal) -> Decimal {
+        Decimal::new(&self.digits * &other.digits, self.scale + other.scale)
+    }
+}
+
+impl PartialEq for Decimal {
+    /// By value, whatever the scales.
+    fn eq(&self, other: &Decimal) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Decimal) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Decimal) -> Ordering {
+        let scale = self.scale.max(other.scale);
+        self.rescaled(scale).cmp(&other.rescaled(scale))
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = Error;
+
+    /// Reads `12.50`, `-3` or `1_000.5`, keeping every digit after the point.
+    fn from_str(text: &str) -> Result<Decimal, Error> {
+        let invalid = || Error::InvalidDecimal(text.to_owned());
+        let cleaned = text.trim().replace('_', "");
+        let (negative, unsigned) = match cleaned.strip_prefix('-') {
+            Some(unsigned) => (true, unsigned),
+            None => (false, cleaned.strip_prefix('+').unwrap_or(&cleaned)),
+        };
+        let (whole, fraction) = match unsigned.find('.') {
+            Some(point) => (&unsigned[..point], &unsigned[point + 1..]),
+            None => (unsigned, ""),
+        };
+        let all_digits = whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit());
+        if !all_digits || whole.len() + fraction.len() == 0 {
+            return Err(invalid());
+        }
+        let digits = BigInt::parse_bytes(format!("{}{}", whole, fraction).as_bytes(), 10)
+            .ok_or_else(invalid)?;
+        let digits = if negative { -digits } else { digits };
+        Ok(Decimal::new(digits, fraction.len() as u32))
+    }
+}
+
+impl fmt::Display for Decimal {
+    /// `12.50`, with as many digits after the point as the scale.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let scale = self.scale as usize;
+        let mut digits = self.digits.abs().to_string();
+        if digits.len() <= scale {
+            digits = "0".repeat(scale + 1 - digits.len()) + &digits;
+        }
+        let (whole, fraction) = digits.split_at(digits.len() - scale);
+        let sign = if self.digits.is_negative() { "-" } else { "" };
+        if fraction.is_empty() {
+            write!(f, "{}{}", sign, whole)
+        } else {
+            write!(f, "{}{}.{}", sign, whole, fraction)
+        }
+    }
+}
@@ -0,0 +1,6 @@
+#[allow(clippy::module_inception)]
+pub mod value;
+pub mod decimal;
+
+pub use self::value::*;
+pub use self::decimal::*;
@@ -0,0 +1,556 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet};
+use std::convert::TryFrom;
+use std::fmt;
+use std::rc::Rc;
+use serde::{Serialize, Serializer};
+use serde::ser::Error as SerializeError;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{FromPrimitive, ToPrimitive};
+use crate::*;
+use self::core::object::{Closure, Instance, Type};
+use self::core::tulis::format_nested;
+use value::Decimal;
+use Error;
+
+/// A BAIK value. Besides plain data it holds what a script can create but JSON
+/// can not express: exact integers of any size, exact decimals and fractions
+/// apart from floats, byte strings, atoms, functions, types, their instances and maps with non-string
+/// keys.
+///
+/// `BigInteger` only holds integers that do not fit in an `i64`, build it with
+/// `Value::from` to keep it that way. A `Rational` is always in lowest terms
+/// with a positive denominator, and stays one even when it is whole.
+///
+/// Arrays and maps are copied on assignment; instances are shared.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    BigInteger(BigInt),
+    Float(f64),
+    Decimal(Decimal),
+    Rational(BigRational),
+    String(String),
+    Bytes(Vec<u8>),
+    Atom(Atom),
+    Array(Vec<Value>),
+    Map(BTreeMap<Key, Value>),
+    Function(Rc<Closure>),
+    Type(Rc<Type>),
+    Instance(Rc<RefCell<Instance>>),
+}
+
+thread_local! {
+    static ATOMS: RefCell<HashSet<Rc<str>>> = RefCell::new(HashSet::new());
+}
+
+/// An interned symbol like `:nama`. Every atom with the same name shares one
+/// allocation, so equality is a pointer comparison.
+#[derive(Clone)]
+pub struct Atom(Rc<str>);
+
+impl Atom {
+    pub fn new(name: &str) -> Atom {
+        ATOMS.with(|atoms| {
+            let mut atoms = atoms.borrow_mut();
+            if let Some(atom) = atoms.get(name) {
+                return Atom(atom.clone());
+            }
+            let atom: Rc<str> = Rc::from(name);
+            atoms.insert(atom.clone());
+            Atom(atom)
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for Atom {
+    fn eq(&self, other: &Atom) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Atom {}
+
+impl PartialOrd for Atom {
+    fn partial_cmp(&self, other: &Atom) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Atom {
+    fn cmp(&self, other: &Atom) -> Ordering {
+        if self == other {
+            Ordering::Equal
+        } else {
+            self.name().cmp(other.name())
+        }
+    }
+}
+
+impl fmt::Debug for Atom {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, ":{}", self.name())
+    }
+}
+
+/// A map key. Keys of different kinds never match, so `"a"` and `:a`, or `1`
+/// and `"1"`, are different keys.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Key {
+    String(String),
+    Atom(Atom),
+    Integer(i64),
+    /// An integer outside the range of an `i64`, see `Value::BigInteger`.
+    BigInteger(BigInt),
+    Bool(bool),
+    Bytes(Vec<u8>),
+}
+
+impl Key {
+    /// The key for a value used as `peta[kunci]` or `kunci => nilai`. Floats,
+    /// collections and the like can not be keys.
+    pub fn from_value(value: &Value) -> Result<Key, Error> {
+        match *value {
+            Value::String(ref string) => Ok(Key::String(string.clone())),
+            Value::Atom(ref atom) => Ok(Key::Atom(atom.clone())),
+            Value::Integer(integer) => Ok(Key::Integer(integer)),
+            Value::BigInteger(ref integer) => Ok(Key::BigInteger(integer.clone())),
+            Value::Bool(boolean) => Ok(Key::Bool(boolean)),
+            Value::Bytes(ref bytes) => Ok(Key::Bytes(bytes.clone())),
+            _ => Err(Error::ExpectedIdentifier),
+        }
+    }
+
+    /// The key as a value, as `untuk k, v dalam peta` binds it.
+    pub fn to_value(&self) -> Value {
+        match *self {
+            Key::String(ref string) => Value::String(string.clone()),
+            Key::Atom(ref atom) => Value::Atom(atom.clone()),
+            Key::Integer(integer) => Value::Integer(integer),
+            Key::BigInteger(ref integer) => Value::BigInteger(integer.clone()),
+            Key::Bool(boolean) => Value::Bool(boolean),
+            Key::Bytes(ref bytes) => Value::Bytes(bytes.clone()),
+        }
+    }
+
+    /// JSON object key. Only string keys have one.
+    fn to_json_key(&self) -> Result<String, Error> {
+        match *self {
+            Key::String(ref string) => Ok(string.clone()),
+            Key::Atom(ref atom) => Err(Error::UnexpectedAtom(atom.name().to_owned())),
+            ref key => Err(Error::UnexpectedKey(key.to_value().to_string())),
+        }
+    }
+}
+
+impl Value {
+    /// Converts to JSON, failing for anything that holds an atom, a function, a
+    /// type, a map key that is not a string or a number JSON can not write
+    /// exactly: NaN, the infinities and fractions like `1/3` with no end after
+    /// the point. Instances become JSON objects of their fields and byte
+    /// strings arrays of their bytes. Numbers keep every digit, so integers of
+    /// any size and decimals read back the same. An instance that holds itself
+    /// fails with `Error::CyclicValue`.
+    pub fn to_json(&self) -> Result<serde_json::Value, Error> {
+        self.to_json_inside(&mut Vec::new())
+    }
+
+    /// `outer` holds the instances `self` is converted inside of.
+    fn to_json_inside(&self,
+                      outer: &mut Vec<*const RefCell<Instance>>)
+                      -> Result<serde_json::Value, Error> {
+        use serde_json::Value as Json;
+
+        Ok(match *self {
+            Value::Null => Json::Null,
+            Value::Bool(boolean) => Json::Bool(boolean),
+            Value::Integer(integer) => Json::from(integer),
+            Value::BigInteger(ref integer) => json_number(&integer.to_string()),
+            Value::Float(float) if !float.is_finite() => {
+                return Err(Error::UnexpectedNumber(self.to_string()))
+            }
+            Value::Float(float) => Json::from(float),
+            Value::Decimal(ref decimal) => json_number(&decimal.to_string()),
+            Value::Rational(ref rational) => {
+                match Decimal::from_rational_exact(rational) {
+                    Some(decimal) => json_number(&decimal.to_string()),
+                    None => return Err(Error::UnexpectedNumber(self.to_string())),
+                }
+            }
+            Value::String(ref string) => Json::String(string.clone()),
+            Value::Bytes(ref bytes) => Json::from(bytes.clone()),
+            Value::Atom(ref atom) => return Err(Error::UnexpectedAtom(atom.name().to_owned())),
+            Value::Array(ref array) => {
+                let items = array.iter().map(|item| item.to_json_inside(outer));
+                Json::Array(items.collect::<Result<_, _>>()?)
+            }
+            Value::Map(ref map) => {
+                let mut object = serde_json::Map::new();
+                for (key, value) in map {
+                    object.insert(key.to_json_key()?, value.to_json_inside(outer)?);
+                }
+                Json::Object(object)
+            }
+            Value::Function(ref closure) => {
+                return Err(Error::UnexpectedFunction(closure.name().to_owned()))
+            }
+            Value::Type(ref ty) => return Err(Error::UnexpectedType(ty.def.name.clone())),
+            Value::Instance(ref instance) if outer.contains(&Rc::as_ptr(instance)) => {
+                return Err(Error::CyclicValue(instance.borrow().ty.def.name.clone()))
+            }
+            Value::Instance(ref instance) => {
+                outer.push(Rc::as_ptr(instance));
+                let mut object = serde_json::Map::new();
+                for (key, value) in &instance.borrow().fields {
+                    object.insert(key.clone(), value.to_json_inside(outer)?);
+                }
+                outer.pop();
+                Json::Object(object)
+            }
+        })
+    }
+
+    /// Name of the value's type, as used in error messages.
+    pub fn type_name(&self) -> String {
+        match *self {
+            Value::Null => "Nihil",
+            Value::Bool(_) => "Boolean",
+            Value::Integer(_) | Value::Float(_) => "Angka",
+            Value::BigInteger(_) => "BilanganBesar",
+            Value::Decimal(_) => "Desimal",
+            Value::Rational(_) => "Pecahan",
+            Value::String(_) => "Teks",
+            Value::Bytes(_) => "Bita",
+            Value::Atom(_) => "Atom",
+            Value::Array(_) => "Larik",
+            Value::Map(_) => "Peta",
+            Value::Function(_) => "Fungsi",
+            Value::Type(_) => "Tipe",
+            Value::Instance(ref instance) => return instance.borrow().ty.def.name.clone(),
+        }.to_owned()
+    }
+
+    /// Whether the value matches `typename`: its own type, or a trait its type
+    /// implements. A `BilanganBesar`, a `Desimal` or a `Pecahan` is an `Angka`
+    /// too.
+    pub fn is_a(&self, typename: &str) -> bool {
+        match *self {
+            Value::Instance(ref instance) if instance.borrow().ty.implements(typename) => true,
+            Value::BigInteger(_) | Value::Decimal(_) | Value::Rational(_) if typename == "Angka" => {
+                true
+            }
+            _ => self.type_name() == typename,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(*self, Value::Null)
+    }
+
+    pub fn is_number(&self) -> bool {
+        matches!(*self,
+                 Value::Integer(_) |
+                 Value::BigInteger(_) |
+                 Value::Float(_) |
+                 Value::Decimal(_) |
+                 Value::Rational(_))
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(boolean) => Some(boolean),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Value::Integer(integer) => Some(integer),
+            _ => None,
+        }
+    }
+
+    /// An integer of any size.
+    pub fn as_bigint(&self) -> Option<BigInt> {
+        match *self {
+            Value::Integer(integer) => Some(BigInt::from(integer)),
+            Value::BigInteger(ref integer) => Some(integer.clone()),
+            _ => None,
+        }
+    }
+
+    /// A decimal, or an integer as one. Floats are not exact, so they are not
+    /// turned into decimals here.
+    pub fn as_decimal(&self) -> Option<Decimal> {
+        match *self {
+            Value::Decimal(ref decimal) => Some(decimal.clone()),
+            _ => self.as_bigint().map(|integer| Decimal::new(integer, 0)),
+        }
+    }
+
+    /// A fraction, or an integer or a decimal as one. Like `as_decimal`, not
+    /// for floats.
+    pub fn as_rational(&self) -> Option<BigRational> {
+        match *self {
+            Value::Rational(ref rational) => Some(rational.clone()),
+            Value::Decimal(ref decimal) => {
+                Some(BigRational::new(decimal.digits().clone(), BigInt::from(10).pow(decimal.scale())))
+            }
+            _ => self.as_bigint().map(BigRational::from_integer),
+        }
+    }
+
+    /// Any number as a float, the nearest one for integers, decimals and
+    /// fractions.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::Integer(integer) => Some(integer as f64),
+            Value::BigInteger(ref integer) => integer.to_f64(),
+            Value::Float(float) => Some(float),
+            Value::Decimal(ref decimal) => Some(decimal.to_f64()),
+            Value::Rational(ref rational) => rational.to_f64(),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::String(ref string) => Some(string),
+            _ => None,
+        }
+    }
+
+    /// Orders two numbers by their exact value, so an integer and a float are
+    /// not rounded to the same float first. `None` if either is not a number
+    /// or is NaN.
+    pub fn compare_numbers(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Integer(a), Value::Integer(b)) => Some(a.cmp(b)),
+            (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+            (Value::Integer(a), Value::Float(b)) => compare_integer_float(*a, *b),
+            (Value::BigInteger(a), Value::Float(b)) => compare_bigint_float(a, *b),
+            (Value::Float(_), _) => other.compare_numbers(self).map(Ordering::reverse),
+            (Value::Rational(a), Value::Float(b)) => compare_rational_float(a, *b),
+            (Value::Rational(a), _) => Some(a.cmp(&other.as_rational()?)),
+            (_, Value::Rational(_)) => other.compare_numbers(self).map(Ordering::reverse),
+            (Value::Decimal(a), Value::Float(b)) => compare_decimal_float(a, *b),
+            (Value::Decimal(a), _) => Some(a.cmp(&other.as_decimal()?)),
+            (_, Value::Decimal(_)) => other.compare_numbers(self).map(Ordering::reverse),
+            _ => Some(self.as_bigint()?.cmp(&other.as_bigint()?)),
+        }
+    }
+}
+
+/// A JSON number with exactly these digits, which `arbitrary_precision` keeps.
+fn json_number(digits: &str) -> serde_json::Value {
+    serde_json::from_str(digits).unwrap()
+}
+
+fn compare_rational_float(rational: &BigRational, float: f64) -> Option<Ordering> {
+    if float.is_infinite() {
+        Some(if float > 0.0 { Ordering::Less } else { Ordering::Greater })
+    } else {
+        Some(rational.cmp(&BigRational::from_float(float)?))
+    }
+}
+
+fn compare_decimal_float(decimal: &Decimal, float: f64) -> Option<Ordering> {
+    if float.is_infinite() {
+        Some(if float > 0.0 { Ordering::Less } else { Ordering::Greater })
+    } else {
+        Some(decimal.cmp(&Decimal::from_f64_exact(float)?))
+    }
+}
+
+fn compare_bigint_float(integer: &BigInt, float: f64) -> Option<Ordering> {
+    if float.is_nan() {
+        None
+    } else if float.is_infinite() {
+        Some(if float > 0.0 { Ordering::Less } else { Ordering::Greater })
+    } else {
+        // `whole` has no fraction, so it converts exactly.
+        let whole = float.trunc();
+        match integer.cmp(&BigInt::from_f64(whole)?) {
+            Ordering::Equal => 0.0.partial_cmp(&(float - whole)),
+            ordering => Some(ordering),
+        }
+    }
+}
+
+fn compare_integer_float(integer: i64, float: f64) -> Option<Ordering> {
+    // 2^63, every integer is below it and every float from -2^63 to it is
+    // either whole and fits an `i64` or has a fraction.
+    const LIMIT: f64 = 9_223_372_036_854_775_808.0;
+
+    if float.is_nan() {
+        None
+    } else if float >= LIMIT {
+        Some(Ordering::Less)
+    } else if float < -LIMIT {
+        Some(Ordering::Greater)
+    } else {
+        let whole = float.trunc();
+        match integer.cmp(&(whole as i64)) {
+            Ordering::Equal => 0.0.partial_cmp(&(float - whole)),
+            ordering => Some(ordering),
+        }
+    }
+}
+
+impl PartialEq for Value {
+    /// `==` in scripts. Numbers compare by exact value, so `1 == 1.0`; functions, types
+    /// and instances by identity; everything else structurally.
+    fn eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Null, Value::Null) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Integer(a), Value::Integer(b)) => a == b,
+            (Value::BigInteger(a), Value::BigInteger(b)) => a == b,
+            (Value::Decimal(a), Value::Decimal(b)) => a == b,
+            (Value::Rational(a), Value::Rational(b)) => a == b,
+            (a, b) if a.is_number() && b.is_number() => a.compare_numbers(b) == Some(Ordering::Equal),
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Bytes(a), Value::Bytes(b)) => a == b,
+            (Value::Atom(a), Value::Atom(b)) => a == b,
+            (Value::Array(a), Value::Array(b)) => a == b,
+            (Value::Map(a), Value::Map(b)) => a == b,
+            (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+            (Value::Type(a), Value::Type(b)) => Rc::ptr_eq(a, b),
+            (Value::Instance(a), Value::Instance(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    /// The value as it would be written in a script, so strings are quoted.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&format_nested(self))
+    }
+}
+
+impl From<serde_json::Value> for Value {
+    /// Integers of any size stay exact, any other number becomes the nearest
+    /// float.
+    fn from(json: serde_json::Value) -> Value {
+        use serde_json::Value as Json;
+
+        match json {
+            Json::Null => Value::Null,
+            Json::Bool(boolean) => Value::Bool(boolean),
+            Json::Number(number) => {
+                match (number.as_i64(), BigInt::parse_bytes(number.to_string().as_bytes(), 10)) {
+                    (Some(integer), _) => Value::Integer(integer),
+                    (None, Some(integer)) => Value::from(integer),
+                    _ => Value::Float(number.as_f64().unwrap_or(f64::NAN)),
+                }
+            }
+            Json::String(string) => Value::String(string),
+            Json::Array(array) => Value::Array(array.into_iter().map(Value::from).collect()),
+            Json::Object(object) => {
+                Value::Map(object.into_iter()
+                    .map(|(key, value)| (Key::String(key), Value::from(value)))
+                    .collect())
+            }
+        }
+    }
+}
+
+impl TryFrom<Value> for serde_json::Value {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<serde_json::Value, Error> {
+        value.to_json()
+    }
+}
+
+impl Serialize for Value {
+    /// Serializes the JSON form, see `to_json`.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_json().map_err(S::Error::custom)?.serialize(serializer)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(boolean: bool) -> Value {
+        Value::Bool(boolean)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(integer: i64) -> Value {
+        Value::Integer(integer)
+    }
+}
+
+impl From<u64> for Value {
+    fn from(integer: u64) -> Value {
+        Value::from(BigInt::from(integer))
+    }
+}
+
+impl From<BigInt> for Value {
+    /// A `BilanganBesar`, or an `Integer` when it fits in an `i64`.
+    fn from(integer: BigInt) -> Value {
+        match integer.to_i64() {
+            Some(integer) => Value::Integer(integer),
+            None => Value::BigInteger(integer),
+        }
+    }
+}
+
+impl From<usize> for Value {
+    fn from(integer: usize) -> Value {
+        Value::from(integer as u64)
+    }
+}
+
+impl From<Decimal> for Value {
+    fn from(decimal: Decimal) -> Value {
+        Value::Decimal(decimal)
+    }
+}
+
+impl From<BigRational> for Value {
+    fn from(rational: BigRational) -> Value {
+        Value::Rational(rational)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(float: f64) -> Value {
+        Value::Float(float)
+    }
+}
+
+impl From<String> for Value {
+    fn from(string: String) -> Value {
+        Value::String(string)
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(bytes: Vec<u8>) -> Value {
+        Value::Bytes(bytes)
+    }
+}
+
+impl<'a> From<&'a str> for Value {
+    fn from(string: &'a str) -> Value {
+        Value::String(string.to_owned())
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(array: Vec<Value>) -> Value {
+        Value::Array(array)
+    }
+}
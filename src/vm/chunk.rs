@@ -0,0 +1,206 @@
+use std::rc::Rc;
+use crate::*;
+use ast::{BinaryOp, MethodKind, UnaryOp};
+use Value;
+
+/// Where a variable lives. Names are indexes into the constants pool.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Place {
+    /// A slot in the frame of the running function.
+    Local(usize),
+    /// A variable of an enclosing function, by its index in the captures of
+    /// the running function.
+    Upvalue(usize),
+    /// The running function, which a named function calls itself by.
+    Itself,
+    /// A variable of the top level of the script, by its index in `Globals`.
+    Global(usize),
+    /// `@nama`, a property of the receiver of the running method.
+    Property(usize),
+}
+
+/// Where a function gets a variable it captures from, when it is created.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Capture {
+    /// A slot of the frame the function is created in.
+    Local(usize),
+    /// A variable the function it is created in captured itself.
+    Upvalue(usize),
+    /// The function it is created in.
+    Itself,
+}
+
+/// One step of the stack machine. Operands are indexes into the chunk's
+/// constants or functions, counts of stack values, or jump targets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Instruction {
+    Constant(usize),
+    Null,
+    Pop,
+    /// Drops values above the given depth, for `berhenti` and `lanjut` in the
+    /// middle of an expression.
+    Truncate(usize),
+    Load(Place),
+    /// Stores the value on top without popping it, assignments are expressions.
+    Store(Place),
+    /// `a[i][j] = v`: pops the path and sets the item in place, keeping `v`.
+    SetItem(Place, usize),
+    Index,
+    Field(usize),
+    Binary(BinaryOp),
+    Unary(UnaryOp),
+    Array(usize),
+    /// Builds a map from this many key and value pairs.
+    Map(usize),
+    Range,
+    TypeName(usize),
+    /// `Tipe{ ... }` from this many property name and value pairs.
+    Constructor(usize, usize),
+    /// `@{ ... }` from this many property name and value pairs.
+    PropertySet(usize),
+    Closure(usize),
+    /// Calls the value below the arguments.
+    Call(usize),
+    /// Calls the global of that index, or the builtin of its name when no such
+    /// global is defined.
+    CallGlobal(usize, usize),
+    /// Calls a method on the value below the arguments.
+    Invoke(usize, usize),
+    /// `@nama(...)`, calls a method on the receiver.
+    InvokeSelf(usize, usize),
+    Tulis(usize),
+    Jump(usize),
+    /// Pops a condition, which has to be a boolean.
+    JumpIfFalse(usize),
+    /// `&&`: jumps keeping the left value when it is `salah`, so the right
+    /// side does not run. Otherwise the right side and `Binary(And)` follow.
+    JumpIfFalseKeep(usize),
+    /// `||`: jumps keeping the left value when it is `benar`.
+    JumpIfTrueKeep(usize),
+    /// Starts an `untuk` over the popped collection, `true` when the loop binds keys.
+    Iterate(bool),
+    /// Starts an `untuk` over the range between the two popped integers.
+    IterateRange,
+    /// Pushes the next key and item, or jumps when there is none.
+    Next(usize),
+    EndIterate,
+    /// Gives the slots from the first to before the second number fresh values
+    /// when a loop body starts again, so each run has variables of its own
+    /// for the functions made in it to capture.
+    ResetSlots(usize, usize),
+    Return,
+    /// Calls a function given to an `Expr`, or a builtin, by name.
+    CallName(usize, usize),
+    /// A variable of an `Expr` context, `nihil` when there is none.
+    Context(usize),
+    /// `a.b.c` in an `Expr`.
+    Dot(usize),
+    /// `a[b][c]` in an `Expr`.
+    Subscript(usize),
+    /// `!` in an `Expr`, where `nihil` counts as `salah`.
+    Falsy,
+    /// A builtin call in an `Expr` that was worked out when compiling: the
+    /// name, the arguments as an array constant and the result. A function of
+    /// that name given to the `Expr` is called instead.
+    Folded(usize, usize, usize),
+}
+
+/// Compiled code with the constants and the functions it refers to.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Chunk {
+    pub code: Vec<Instruction>,
+    pub constants: Vec<Value>,
+    pub functions: Vec<Rc<FunctionProto>>,
+}
+
+impl Chunk {
+    pub fn add_constant(&mut self, value: Value) -> usize {
+        self.constants.push(value);
+        self.constants.len() - 1
+    }
+
+    /// Index of a name in the constants, added once however often it is used.
+    pub fn add_name(&mut self, name: &str) -> usize {
+        let found = self.constants.iter().position(|constant| match *constant {
+            Value::String(ref string) => string == name,
+            _ => false,
+        });
+        match found {
+            Some(index) => index,
+            None => self.add_constant(Value::String(name.to_owned())),
+        }
+    }
+
+    /// The name at `index`, added by `add_name`.
+    pub fn name(&self, index: usize) -> &str {
+        match self.constants[index] {
+            Value::String(ref name) => name,
+            ref constant => panic!("constant {} is not a name: {:?}", index, constant),
+        }
+    }
+}
+
+/// The code of one function clause or method body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Proto {
+    pub arity: usize,
+    /// Frame slots: the arguments first, then every local of the body.
+    pub slots: usize,
+    pub chunk: Chunk,
+}
+
+/// A compiled `fungsi` with one `Proto` per clause, and the variables of the
+/// functions around it that its clauses use.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionProto {
+    pub name: Option<String>,
+    pub captures: Vec<Capture>,
+    pub clauses: Vec<Rc<Proto>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Method {
+    pub kind: MethodKind,
+    pub name: String,
+    pub predicate: bool,
+    pub proto: Rc<Proto>,
+}
+
+/// A method a trait requires, without a body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spec {
+    pub kind: MethodKind,
+    pub name: String,
+    pub arity: usize,
+    pub returns: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImplProto {
+    pub trait_name: String,
+    pub methods: Vec<Method>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeProto {
+    pub name: String,
+    pub props: Vec<String>,
+    pub methods: Vec<Method>,
+    pub impls: Vec<ImplProto>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraitProto {
+    pub name: String,
+    pub requires: Vec<String>,
+    pub specs: Vec<Spec>,
+    pub methods: Vec<Method>,
+}
+
+/// A compiled program: its definitions and the code of its top level.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Module {
+    pub traits: Vec<TraitProto>,
+    pub types: Vec<TypeProto>,
+    pub main: Rc<Proto>,
+}
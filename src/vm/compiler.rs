@@ -0,0 +1,733 @@
+use std::collections::HashSet;
+use std::mem;
+use std::rc::Rc;
+use crate::*;
+use ast::{BinaryOp, Block, Expr, ExprKind, For, FunctionDef, MapKey, MethodDef, MethodSpec, Program,
+          Stmt, TraitDef, TypeDef};
+use value::Atom;
+use vm::vm::Globals;
+use vm::chunk::{Capture, Chunk, FunctionProto, ImplProto, Instruction, Method, Module, Place, Proto,
+                Spec, TraitProto, TypeProto};
+use Value;
+use Error;
+
+/// Compiles a parsed program to bytecode.
+///
+/// Variables of the top level are globals, which get an index in `globals` so
+/// running code does not look them up by name. The module can only run on the
+/// VM `globals` belongs to. Everything bound inside a block or a function gets
+/// a slot in the frame of its function. A function captures the variables of
+/// the functions around it that it uses, and reaches them through
+/// `Place::Upvalue`; it does not keep their frames.
+///
+/// Inside its body the name of a named local function means the function
+/// itself, `Place::Itself`. A function that calls itself would otherwise
+/// capture the variable holding it, and the two would keep each other alive.
+///
+/// An assignment inside a block or function changes a global when one of that
+/// name exists, from this program or an earlier run, otherwise it binds a new
+/// local.
+pub fn compile(program: &Program, globals: &mut Globals) -> Result<Module, Error> {
+    let mut declared = HashSet::new();
+    for stmt in &program.stmts {
+        if let Stmt::Expr(ref expr) = *stmt {
+            match expr.kind {
+                ExprKind::Declaration(ref name, _) | ExprKind::Let(ref name, _) => {
+                    declared.insert(name.clone());
+                }
+                ExprKind::Function(FunctionDef { name: Some(ref name), .. }) => {
+                    declared.insert(name.clone());
+                }
+                _ => (),
+            }
+        }
+    }
+    let mut compiler = Compiler {
+        functions: vec![FunctionState::main()],
+        declared: &declared,
+        globals,
+    };
+
+    let mut traits = Vec::new();
+    let mut types = Vec::new();
+    let mut exprs = 0;
+    for stmt in &program.stmts {
+        match *stmt {
+            Stmt::Trait(ref traitdef) => traits.push(compiler.compile_trait(traitdef)?),
+            Stmt::Type(ref typedef) => types.push(compiler.compile_type(typedef)?),
+            Stmt::Expr(ref expr) => {
+                if exprs > 0 {
+                    compiler.emit(Instruction::Pop);
+                }
+                compiler.compile_expr(expr)?;
+                exprs += 1;
+            }
+        }
+    }
+    if exprs == 0 {
+        compiler.emit(Instruction::Null);
+    }
+    compiler.emit(Instruction::Return);
+
+    let main = compiler.functions.pop().unwrap().finish(0);
+    Ok(Module {
+        traits,
+        types,
+        main: Rc::new(main),
+    })
+}
+
+/// A loop being compiled, for `berhenti` and `lanjut`.
+struct Loop {
+    start: usize,
+    /// Stack depth when the body starts.
+    depth: usize,
+    breaks: Vec<usize>,
+}
+
+/// A function being compiled.
+struct FunctionState {
+    /// Names bound in each open block, innermost last.
+    scopes: Vec<Vec<(String, usize)>>,
+    slots: usize,
+    /// The name the function calls itself by.
+    name: Option<String>,
+    /// The variables of enclosing functions it uses, shared by its clauses.
+    captures: Vec<Capture>,
+    chunk: Chunk,
+    loops: Vec<Loop>,
+    /// Values on the stack at this point of the code.
+    depth: usize,
+}
+
+impl FunctionState {
+    fn new(args: Vec<String>) -> FunctionState {
+        let slots = args.len();
+        FunctionState {
+            scopes: vec![args.into_iter().enumerate().map(|(slot, arg)| (arg, slot)).collect()],
+            slots,
+            name: None,
+            captures: Vec::new(),
+            chunk: Chunk::default(),
+            loops: Vec::new(),
+            depth: 0,
+        }
+    }
+
+    /// The top level, where nothing is bound in a block until one opens.
+    fn main() -> FunctionState {
+        FunctionState { scopes: Vec::new(), ..FunctionState::new(Vec::new()) }
+    }
+
+    /// The index of `capture` in the function's captures, added once.
+    fn capture(&mut self, capture: Capture) -> usize {
+        match self.captures.iter().position(|&captured| captured == capture) {
+            Some(index) => index,
+            None => {
+                self.captures.push(capture);
+                self.captures.len() - 1
+            }
+        }
+    }
+
+    fn finish(self, arity: usize) -> Proto {
+        Proto {
+            arity,
+            slots: self.slots,
+            chunk: self.chunk,
+        }
+    }
+}
+
+struct Compiler<'a> {
+    /// The function being compiled last, the ones it is nested in before it.
+    functions: Vec<FunctionState>,
+    /// Globals the program itself defines.
+    declared: &'a HashSet<String>,
+    globals: &'a mut Globals,
+}
+
+impl<'a> Compiler<'a> {
+    fn current(&mut self) -> &mut FunctionState {
+        self.functions.last_mut().unwrap()
+    }
+
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        let current = self.current();
+        current.depth = (current.depth as isize + stack_effect(instruction)) as usize;
+        current.chunk.code.push(instruction);
+        current.chunk.code.len() - 1
+    }
+
+    /// Points the jump at `at` to the next instruction.
+    fn patch(&mut self, at: usize) {
+        let current = self.current();
+        let target = current.chunk.code.len();
+        current.chunk.code[at] = match current.chunk.code[at] {
+            Instruction::Jump(_) => Instruction::Jump(target),
+            Instruction::JumpIfFalse(_) => Instruction::JumpIfFalse(target),
+            Instruction::JumpIfFalseKeep(_) => Instruction::JumpIfFalseKeep(target),
+            Instruction::JumpIfTrueKeep(_) => Instruction::JumpIfTrueKeep(target),
+            Instruction::Next(_) => Instruction::Next(target),
+            instruction => panic!("not a jump: {:?}", instruction),
+        };
+    }
+
+    /// Extends the `ResetSlots` at `at` over every slot declared since.
+    fn patch_reset(&mut self, at: usize) {
+        let current = self.current();
+        current.chunk.code[at] = match current.chunk.code[at] {
+            Instruction::ResetSlots(first, _) => Instruction::ResetSlots(first, current.slots),
+            instruction => panic!("not a reset: {:?}", instruction),
+        };
+    }
+
+    fn constant(&mut self, value: Value) {
+        let index = self.current().chunk.add_constant(value);
+        self.emit(Instruction::Constant(index));
+    }
+
+    fn name(&mut self, name: &str) -> usize {
+        self.current().chunk.add_name(name)
+    }
+
+    /// Whether code is running directly at the top level, where variables are globals.
+    fn at_top_level(&self) -> bool {
+        self.functions.len() == 1 && self.functions[0].scopes.is_empty()
+    }
+
+    /// The variable `name` in the function being compiled, or in one around it,
+    /// which is then captured by every function in between. Only reading
+    /// finds a function's own name, see `compile`.
+    fn resolve(&mut self, name: &str, reading: bool) -> Option<Place> {
+        let level = self.functions.len() - 1;
+        self.resolve_at(level, name, reading)
+    }
+
+    fn resolve_at(&mut self, level: usize, name: &str, reading: bool) -> Option<Place> {
+        let function = &self.functions[level];
+        let found = function.scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev())
+            .find(|binding| binding.0 == name);
+        if let Some(&(_, slot)) = found {
+            return Some(Place::Local(slot));
+        }
+        if reading && function.name.as_deref() == Some(name) {
+            return Some(Place::Itself);
+        }
+        if level == 0 {
+            return None;
+        }
+        let capture = match self.resolve_at(level - 1, name, reading)? {
+            Place::Local(slot) => Capture::Local(slot),
+            Place::Upvalue(index) => Capture::Upvalue(index),
+            Place::Itself => Capture::Itself,
+            place => return Some(place),
+        };
+        Some(Place::Upvalue(self.functions[level].capture(capture)))
+    }
+
+    fn resolve_or_global(&mut self, name: &str, reading: bool) -> Place {
+        match self.resolve(name, reading) {
+            Some(place) => place,
+            None => Place::Global(self.globals.index(name)),
+        }
+    }
+
+    /// Binds `name` in the innermost block, or as a global at the top level.
+    fn declare(&mut self, name: &str) -> Place {
+        if self.at_top_level() {
+            return Place::Global(self.globals.index(name));
+        }
+        let current = self.current();
+        let slot = current.slots;
+        current.slots += 1;
+        current.scopes.last_mut().unwrap().push((name.to_owned(), slot));
+        Place::Local(slot)
+    }
+
+    /// Where `name = v` stores: the nearest existing binding, or a new one.
+    fn assignment(&mut self, name: &str) -> Place {
+        if let Some(place) = self.resolve(name, false) {
+            return place;
+        }
+        if self.declared.contains(name) || self.globals.is_defined(name) {
+            return Place::Global(self.globals.index(name));
+        }
+        self.declare(name)
+    }
+
+    fn compile_block(&mut self, block: &Block) -> Result<(), Error> {
+        self.current().scopes.push(Vec::new());
+        if block.exprs.is_empty() {
+            self.emit(Instruction::Null);
+        }
+        for (index, expr) in block.exprs.iter().enumerate() {
+            if index > 0 {
+                self.emit(Instruction::Pop);
+            }
+            self.compile_expr(expr)?;
+        }
+        self.current().scopes.pop();
+        Ok(())
+    }
+
+    fn compile_all(&mut self, exprs: &[Expr]) -> Result<usize, Error> {
+        for expr in exprs {
+            self.compile_expr(expr)?;
+        }
+        Ok(exprs.len())
+    }
+
+    fn compile_expr(&mut self, expr: &Expr) -> Result<(), Error> {
+        match expr.kind {
+            ExprKind::Integer(integer) => self.constant(Value::from(integer)),
+            ExprKind::BigInteger(ref integer) => self.constant(Value::from(integer.clone())),
+            ExprKind::Decimal(ref decimal) => self.constant(Value::Decimal(decimal.clone())),
+            ExprKind::Float(float) => self.constant(Value::Float(float)),
+            ExprKind::String(ref string) => self.constant(Value::String(string.clone())),
+            ExprKind::Boolean(boolean) => self.constant(Value::Bool(boolean)),
+            ExprKind::Atom(ref name) => self.constant(Value::Atom(Atom::new(name))),
+            ExprKind::TypeName(ref typename) => {
+                let name = self.name(typename);
+                self.emit(Instruction::TypeName(name));
+            }
+            ExprKind::Array(ref items) => {
+                let len = self.compile_all(items)?;
+                self.emit(Instruction::Array(len));
+            }
+            ExprKind::Map(ref pairs) => {
+                for (key, value) in pairs {
+                    match *key {
+                        MapKey::Keyword(ref keyword) => self.constant(Value::String(keyword.clone())),
+                        MapKey::Expr(ref key) => self.compile_expr(key)?,
+                    }
+                    self.compile_expr(value)?;
+                }
+                self.emit(Instruction::Map(pairs.len()));
+            }
+            ExprKind::Constructor(ref typename, ref props) => {
+                self.compile_properties(props)?;
+                let name = self.name(typename);
+                self.emit(Instruction::Constructor(name, props.len()));
+            }
+            ExprKind::Local(ref name) => {
+                let place = self.resolve_or_global(name, true);
+                self.emit(Instruction::Load(place));
+            }
+            ExprKind::PropertyGet(ref property) => {
+                let name = self.name(property);
+                self.emit(Instruction::Load(Place::Property(name)));
+            }
+            ExprKind::PropertySet(ref props) => {
+                self.compile_properties(props)?;
+                self.emit(Instruction::PropertySet(props.len()));
+            }
+            ExprKind::Declaration(ref name, ref value) => {
+                self.compile_expr(value)?;
+                let place = self.assignment(name);
+                self.emit(Instruction::Store(place));
+            }
+            ExprKind::Let(ref name, ref value) => {
+                self.compile_expr(value)?;
+                let place = self.declare(name);
+                self.emit(Instruction::Store(place));
+            }
+            ExprKind::Binary(op, ref lhs, ref rhs) => {
+                self.compile_expr(lhs)?;
+                let skip = match op {
+                    BinaryOp::And => Some(self.emit(Instruction::JumpIfFalseKeep(0))),
+                    BinaryOp::Or => Some(self.emit(Instruction::JumpIfTrueKeep(0))),
+                    _ => None,
+                };
+                self.compile_expr(rhs)?;
+                self.emit(Instruction::Binary(op));
+                if let Some(skip) = skip {
+                    self.patch(skip);
+                }
+            }
+            ExprKind::Unary(op, ref value) => {
+                self.compile_expr(value)?;
+                self.emit(Instruction::Unary(op));
+            }
+            ExprKind::Call(ref callee, ref args) => self.compile_call(callee, args)?,
+            ExprKind::MethodCall(ref receiver, ref method, ref args) => {
+                self.compile_expr(receiver)?;
+                let argc = self.compile_all(args)?;
+                let name = self.name(method);
+                self.emit(Instruction::Invoke(name, argc));
+            }
+            ExprKind::Index(ref target, ref index) => {
+                self.compile_expr(target)?;
+                self.compile_expr(index)?;
+                self.emit(Instruction::Index);
+            }
+            ExprKind::Field(ref target, ref field) => {
+                self.compile_expr(target)?;
+                let name = self.name(field);
+                self.emit(Instruction::Field(name));
+            }
+            ExprKind::Assign(ref target, ref value) => self.compile_assign(target, value)?,
+            ExprKind::If(ref if_expr) => {
+                let mut ends = Vec::new();
+                let branches = Some((&if_expr.condition, &if_expr.positive))
+                    .into_iter()
+                    .chain(if_expr.branches.iter().map(|(condition, block)| (condition, block)));
+                for (condition, block) in branches {
+                    self.compile_expr(condition)?;
+                    let next = self.emit(Instruction::JumpIfFalse(0));
+                    self.compile_block(block)?;
+                    ends.push(self.emit(Instruction::Jump(0)));
+                    self.current().depth -= 1;
+                    self.patch(next);
+                }
+                match if_expr.negative {
+                    Some(ref block) => self.compile_block(block)?,
+                    None => {
+                        self.emit(Instruction::Null);
+                    }
+                }
+                for end in ends {
+                    self.patch(end);
+                }
+            }
+            ExprKind::While(ref condition, ref body) => {
+                let start = self.current().chunk.code.len();
+                self.compile_expr(condition)?;
+                let exit = self.emit(Instruction::JumpIfFalse(0));
+                let first = self.current().slots;
+                let reset = self.emit(Instruction::ResetSlots(first, first));
+                self.compile_loop_body(start, body)?;
+                self.patch_reset(reset);
+                self.patch(exit);
+                self.finish_loop();
+                self.emit(Instruction::Null);
+            }
+            ExprKind::For(ref for_loop) => self.compile_for(for_loop)?,
+            ExprKind::Range(ref start, ref end) => {
+                self.compile_expr(start)?;
+                self.compile_expr(end)?;
+                self.emit(Instruction::Range);
+            }
+            ExprKind::Return(ref value) => {
+                match *value {
+                    Some(ref value) => self.compile_expr(value)?,
+                    None => {
+                        self.emit(Instruction::Null);
+                    }
+                }
+                self.emit(Instruction::Return);
+                // Code after `kembali` is never reached, but still has to balance.
+                self.current().depth += 1;
+            }
+            ExprKind::Break => {
+                let depth = match self.current().loops.last() {
+                    Some(current) => current.depth,
+                    None => return Err(Error::BreakOutsideLoop(expr.span.line)),
+                };
+                let saved = self.current().depth;
+                self.truncate(depth);
+                let jump = self.emit(Instruction::Jump(0));
+                self.current().loops.last_mut().unwrap().breaks.push(jump);
+                // Like `kembali`, the jump is an expression that is never finished.
+                self.current().depth = saved + 1;
+            }
+            ExprKind::Continue => {
+                let (start, depth) = match self.current().loops.last() {
+                    Some(current) => (current.start, current.depth),
+                    None => return Err(Error::ContinueOutsideLoop(expr.span.line)),
+                };
+                let saved = self.current().depth;
+                self.truncate(depth);
+                self.emit(Instruction::Jump(start));
+                self.current().depth = saved + 1;
+            }
+            ExprKind::Function(ref function) => self.compile_function(function)?,
+            ExprKind::Tulis(ref args) => {
+                let len = self.compile_all(args)?;
+                self.emit(Instruction::Tulis(len));
+            }
+        }
+        Ok(())
+    }
+
+    fn truncate(&mut self, depth: usize) {
+        if self.current().depth > depth {
+            self.emit(Instruction::Truncate(depth));
+            self.current().depth = depth;
+        }
+    }
+
+    fn compile_properties(&mut self, props: &[(String, Expr)]) -> Result<(), Error> {
+        for (property, value) in props {
+            self.constant(Value::String(property.clone()));
+            self.compile_expr(value)?;
+        }
+        Ok(())
+    }
+
+    /// A call of a local variable, of a global or builtin by name, of a method
+    /// of the receiver, or of any other value. A builtin like `Desimal` can be
+    /// called by a type name.
+    fn compile_call(&mut self, callee: &Expr, args: &[Expr]) -> Result<(), Error> {
+        match callee.kind {
+            ExprKind::Local(ref ident) => {
+                match self.resolve(ident, true) {
+                    Some(place) => {
+                        self.emit(Instruction::Load(place));
+                        let argc = self.compile_all(args)?;
+                        self.emit(Instruction::Call(argc));
+                    }
+                    None => {
+                        let argc = self.compile_all(args)?;
+                        let global = self.globals.index(ident);
+                        self.emit(Instruction::CallGlobal(global, argc));
+                    }
+                }
+            }
+            ExprKind::TypeName(ref name) => {
+                let argc = self.compile_all(args)?;
+                let global = self.globals.index(name);
+                self.emit(Instruction::CallGlobal(global, argc));
+            }
+            ExprKind::PropertyGet(ref method) => {
+                let argc = self.compile_all(args)?;
+                let name = self.name(method);
+                self.emit(Instruction::InvokeSelf(name, argc));
+            }
+            _ => {
+                self.compile_expr(callee)?;
+                let argc = self.compile_all(args)?;
+                self.emit(Instruction::Call(argc));
+            }
+        }
+        Ok(())
+    }
+
+    /// `a[i][j] = v`, `m.k = v` and `@items[i] = v` change the variable in place.
+    fn compile_assign(&mut self, target: &Expr, value: &Expr) -> Result<(), Error> {
+        let mut steps = Vec::new();
+        let mut root = target;
+        while let ExprKind::Index(ref inner, _) | ExprKind::Field(ref inner, _) = root.kind {
+            steps.push(root);
+            root = inner;
+        }
+
+        let place = match root.kind {
+            ExprKind::Local(ref ident) => self.resolve_or_global(ident, false),
+            ExprKind::PropertyGet(ref property) => Place::Property(self.name(property)),
+            ref kind => return Err(Error::UnsupportedExpression(format!("{:?}", kind))),
+        };
+        for step in steps.iter().rev() {
+            match step.kind {
+                ExprKind::Field(_, ref field) => self.constant(Value::String(field.clone())),
+                ExprKind::Index(_, ref index) => self.compile_expr(index)?,
+                _ => unreachable!(),
+            }
+        }
+        self.compile_expr(value)?;
+        self.emit(Instruction::SetItem(place, steps.len()));
+        Ok(())
+    }
+
+    /// Compiles the body of a loop whose condition or next item was checked at
+    /// `start`. The loop stays open for `finish_loop` to patch its `berhenti`s.
+    fn compile_loop_body(&mut self, start: usize, body: &Block) -> Result<(), Error> {
+        let depth = self.current().depth;
+        self.current().loops.push(Loop {
+            start,
+            depth,
+            breaks: Vec::new(),
+        });
+        self.compile_block(body)?;
+        self.emit(Instruction::Pop);
+        self.emit(Instruction::Jump(start));
+        Ok(())
+    }
+
+    fn finish_loop(&mut self) {
+        let finished = self.current().loops.pop().unwrap();
+        for jump in finished.breaks {
+            self.patch(jump);
+        }
+    }
+
+    fn compile_for(&mut self, for_loop: &For) -> Result<(), Error> {
+        match for_loop.collection.kind {
+            ExprKind::Range(ref start, ref end) => {
+                self.compile_expr(start)?;
+                self.compile_expr(end)?;
+                self.emit(Instruction::IterateRange);
+            }
+            _ => {
+                self.compile_expr(&for_loop.collection)?;
+                self.emit(Instruction::Iterate(for_loop.key.is_some()));
+            }
+        }
+
+        self.current().scopes.push(Vec::new());
+        let first = self.current().slots;
+        let key = for_loop.key.as_ref().map(|key| self.declare(key));
+        let item = self.declare(&for_loop.item);
+
+        let start = self.current().chunk.code.len();
+        let next = self.emit(Instruction::Next(0));
+        let reset = self.emit(Instruction::ResetSlots(first, first));
+        self.emit(Instruction::Store(item));
+        self.emit(Instruction::Pop);
+        if let Some(key) = key {
+            self.emit(Instruction::Store(key));
+        }
+        self.emit(Instruction::Pop);
+        self.compile_loop_body(start, &for_loop.body)?;
+        self.patch_reset(reset);
+        self.patch(next);
+        self.finish_loop();
+        self.emit(Instruction::EndIterate);
+        self.emit(Instruction::Null);
+        self.current().scopes.pop();
+        Ok(())
+    }
+
+    /// Compiles a `fungsi` to a closure. A named function is bound before its
+    /// clauses are compiled, so it can call itself.
+    fn compile_function(&mut self, function: &FunctionDef) -> Result<(), Error> {
+        let place = function.name.as_ref().map(|name| self.declare(name));
+        let name = match place {
+            Some(Place::Local(_)) => function.name.clone(),
+            _ => None,
+        };
+
+        let mut clauses = Vec::new();
+        let mut captures = Vec::new();
+        for clause in &function.clauses {
+            self.functions.push(FunctionState {
+                name: name.clone(),
+                captures,
+                ..FunctionState::new(clause.args.clone())
+            });
+            let compiled = self.compile_block(&clause.body).map(|_| {
+                self.emit(Instruction::Return);
+            });
+            let mut state = self.functions.pop().unwrap();
+            compiled?;
+            captures = mem::take(&mut state.captures);
+            clauses.push(Rc::new(state.finish(clause.args.len())));
+        }
+
+        let proto = FunctionProto {
+            name: function.name.clone(),
+            captures,
+            clauses,
+        };
+        let current = self.current();
+        current.chunk.functions.push(Rc::new(proto));
+        let index = current.chunk.functions.len() - 1;
+        self.emit(Instruction::Closure(index));
+        if let Some(place) = place {
+            self.emit(Instruction::Store(place));
+        }
+        Ok(())
+    }
+
+    /// Methods run with only the globals around them.
+    fn compile_method(&mut self, method: &MethodDef) -> Result<Method, Error> {
+        let outer = mem::replace(&mut self.functions, vec![FunctionState::new(method.args.clone())]);
+        let compiled = self.compile_block(&method.body).map(|_| {
+            self.emit(Instruction::Return);
+        });
+        let state = mem::replace(&mut self.functions, outer).pop().unwrap();
+        compiled?;
+
+        Ok(Method {
+            kind: method.kind,
+            name: method.name.clone(),
+            predicate: method.predicate,
+            proto: Rc::new(state.finish(method.args.len())),
+        })
+    }
+
+    fn compile_methods(&mut self, methods: &[MethodDef]) -> Result<Vec<Method>, Error> {
+        methods.iter().map(|method| self.compile_method(method)).collect()
+    }
+
+    fn compile_type(&mut self, typedef: &TypeDef) -> Result<TypeProto, Error> {
+        let mut impls = Vec::new();
+        for impldef in &typedef.impls {
+            impls.push(ImplProto {
+                trait_name: impldef.trait_name.clone(),
+                methods: self.compile_methods(&impldef.methods)?,
+            });
+        }
+        Ok(TypeProto {
+            name: typedef.name.clone(),
+            props: typedef.props.clone(),
+            methods: self.compile_methods(&typedef.methods)?,
+            impls,
+        })
+    }
+
+    fn compile_trait(&mut self, traitdef: &TraitDef) -> Result<TraitProto, Error> {
+        Ok(TraitProto {
+            name: traitdef.name.clone(),
+            requires: traitdef.requires.clone(),
+            specs: traitdef.specs.iter().map(compile_spec).collect(),
+            methods: self.compile_methods(&traitdef.methods)?,
+        })
+    }
+}
+
+fn compile_spec(spec: &MethodSpec) -> Spec {
+    Spec {
+        kind: spec.kind,
+        name: spec.name.clone(),
+        arity: spec.args.len(),
+        returns: spec.returns.clone(),
+    }
+}
+
+/// How many values an instruction leaves on the stack, less the ones it takes.
+fn stack_effect(instruction: Instruction) -> isize {
+    match instruction {
+        Instruction::Constant(_) |
+        Instruction::Null |
+        Instruction::Load(_) |
+        Instruction::TypeName(_) |
+        Instruction::Closure(_) |
+        Instruction::Context(_) |
+        Instruction::Folded(..) => 1,
+        Instruction::Store(_) |
+        Instruction::Field(_) |
+        Instruction::Unary(_) |
+        Instruction::Truncate(_) |
+        Instruction::Jump(_) |
+        Instruction::JumpIfFalseKeep(_) |
+        Instruction::JumpIfTrueKeep(_) |
+        Instruction::EndIterate |
+        Instruction::ResetSlots(..) |
+        Instruction::Falsy => 0,
+        Instruction::Pop |
+        Instruction::Index |
+        Instruction::Binary(_) |
+        Instruction::Range |
+        Instruction::JumpIfFalse(_) |
+        Instruction::Iterate(_) |
+        Instruction::Return => -1,
+        Instruction::IterateRange => -2,
+        Instruction::Next(_) => 2,
+        Instruction::SetItem(_, len) => -(len as isize),
+        Instruction::Array(len) |
+        Instruction::Tulis(len) |
+        Instruction::CallName(_, len) |
+        Instruction::CallGlobal(_, len) |
+        Instruction::InvokeSelf(_, len) |
+        Instruction::Dot(len) |
+        Instruction::Subscript(len) => 1 - len as isize,
+        Instruction::Map(len) | Instruction::Constructor(_, len) | Instruction::PropertySet(len) => {
+            1 - 2 * len as isize
+        }
+        Instruction::Call(len) | Instruction::Invoke(_, len) => -(len as isize),
+    }
+}
@@ -0,0 +1,801 @@
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::Zero;
+use crate::*;
+use ast::{BinaryOp, MethodKind, UnaryOp};
+use value::{Atom, Decimal, Key};
+use vm::chunk::{Capture, Chunk, FunctionProto, ImplProto, Instruction, Method, Module, Place, Proto,
+                Spec, TraitProto, TypeProto};
+use vm::vm::Globals;
+use Value;
+use Error;
+
+/// The first bytes of every `.inac` file.
+pub const MAGIC: &[u8; 4] = b"INAC";
+
+/// Version of the `.inac` layout. Raise it whenever `Instruction` or anything
+/// else written here changes, so older files are refused instead of misread.
+pub const FORMAT_VERSION: u16 = 8;
+
+const HEADER_LEN: usize = 10;
+
+/// Whether `bytes` start like a compiled program.
+pub fn is_inac(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Writes a compiled program as an `.inac` file: `MAGIC`, the format version,
+/// a CRC-32 of the rest, then the names of the globals in `globals` and the
+/// module itself. Numbers are little-endian.
+pub fn encode(module: &Module, globals: &Globals) -> Result<Vec<u8>, Error> {
+    let mut payload = Writer { bytes: Vec::new() };
+    payload.len(globals.names().len());
+    for name in globals.names() {
+        payload.string(name);
+    }
+    payload.module(module)?;
+
+    let mut bytes = Vec::with_capacity(HEADER_LEN + payload.bytes.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&crc32(&payload.bytes).to_le_bytes());
+    bytes.extend_from_slice(&payload.bytes);
+    Ok(bytes)
+}
+
+/// Reads a program written by `encode`. Its globals get indexes in `globals`,
+/// so the module runs on the VM they belong to.
+pub fn decode(bytes: &[u8], globals: &mut Globals) -> Result<Module, Error> {
+    if !is_inac(bytes) || bytes.len() < 6 {
+        return Err(invalid("not a compiled BAIK program"));
+    }
+    let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+    if version != FORMAT_VERSION {
+        return Err(Error::BytecodeVersion(version, FORMAT_VERSION));
+    }
+    if bytes.len() < HEADER_LEN {
+        return Err(invalid("unexpected end of file"));
+    }
+    let checksum = u32::from_le_bytes([bytes[6], bytes[7], bytes[8], bytes[9]]);
+    let payload = &bytes[HEADER_LEN..];
+    if crc32(payload) != checksum {
+        return Err(invalid("checksum mismatch"));
+    }
+
+    let mut reader = Reader {
+        bytes: payload,
+        pos: 0,
+        globals: Vec::new(),
+    };
+    let len = reader.len()?;
+    for _ in 0..len {
+        let name = reader.string()?;
+        reader.globals.push(globals.index(&name));
+    }
+    let module = reader.module()?;
+    if reader.pos != payload.len() {
+        return Err(invalid("trailing bytes"));
+    }
+    Ok(module)
+}
+
+fn invalid(detail: &str) -> Error {
+    Error::InvalidBytecode(detail.to_owned())
+}
+
+/// CRC-32 as used by zip and PNG.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+const BINARY_OPS: [BinaryOp; 20] = [BinaryOp::Pow,
+                                    BinaryOp::Mul,
+                                    BinaryOp::Div,
+                                    BinaryOp::Rem,
+                                    BinaryOp::Add,
+                                    BinaryOp::Sub,
+                                    BinaryOp::Shl,
+                                    BinaryOp::Shr,
+                                    BinaryOp::Lt,
+                                    BinaryOp::Le,
+                                    BinaryOp::Gt,
+                                    BinaryOp::Ge,
+                                    BinaryOp::Ne,
+                                    BinaryOp::Eq,
+                                    BinaryOp::And,
+                                    BinaryOp::Or,
+                                    BinaryOp::BitAnd,
+                                    BinaryOp::BitOr,
+                                    BinaryOp::BitXor,
+                                    BinaryOp::IntDiv];
+
+const UNARY_OPS: [UnaryOp; 3] = [UnaryOp::Neg, UnaryOp::Plus, UnaryOp::Not];
+
+const METHOD_KINDS: [MethodKind; 3] = [MethodKind::Public, MethodKind::Private, MethodKind::Static];
+
+/// Finds the code of `item` in one of the tables above.
+fn code_of<T: PartialEq>(table: &[T], item: &T) -> u8 {
+    table.iter().position(|other| other == item).unwrap() as u8
+}
+
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn byte(&mut self, byte: u8) {
+        self.bytes.push(byte);
+    }
+
+    fn len(&mut self, len: usize) {
+        self.bytes.extend_from_slice(&(len as u32).to_le_bytes());
+    }
+
+    fn string(&mut self, string: &str) {
+        self.len(string.len());
+        self.bytes.extend_from_slice(string.as_bytes());
+    }
+
+    fn bigint(&mut self, integer: &BigInt) {
+        let bytes = integer.to_signed_bytes_le();
+        self.len(bytes.len());
+        self.bytes.extend_from_slice(&bytes);
+    }
+
+    fn strings(&mut self, strings: &[String]) {
+        self.len(strings.len());
+        for string in strings {
+            self.string(string);
+        }
+    }
+
+    fn module(&mut self, module: &Module) -> Result<(), Error> {
+        self.len(module.traits.len());
+        for traitproto in &module.traits {
+            self.string(&traitproto.name);
+            self.strings(&traitproto.requires);
+            self.len(traitproto.specs.len());
+            for spec in &traitproto.specs {
+                self.spec(spec);
+            }
+            self.methods(&traitproto.methods)?;
+        }
+        self.len(module.types.len());
+        for typeproto in &module.types {
+            self.string(&typeproto.name);
+            self.strings(&typeproto.props);
+            self.methods(&typeproto.methods)?;
+            self.len(typeproto.impls.len());
+            for implproto in &typeproto.impls {
+                self.string(&implproto.trait_name);
+                self.methods(&implproto.methods)?;
+            }
+        }
+        self.proto(&module.main)
+    }
+
+    fn spec(&mut self, spec: &Spec) {
+        self.byte(code_of(&METHOD_KINDS, &spec.kind));
+        self.string(&spec.name);
+        self.len(spec.arity);
+        match spec.returns {
+            Some(ref returns) => {
+                self.byte(1);
+                self.strings(returns);
+            }
+            None => self.byte(0),
+        }
+    }
+
+    fn methods(&mut self, methods: &[Method]) -> Result<(), Error> {
+        self.len(methods.len());
+        for method in methods {
+            self.byte(code_of(&METHOD_KINDS, &method.kind));
+            self.string(&method.name);
+            self.byte(method.predicate as u8);
+            self.proto(&method.proto)?;
+        }
+        Ok(())
+    }
+
+    fn proto(&mut self, proto: &Proto) -> Result<(), Error> {
+        self.len(proto.arity);
+        self.len(proto.slots);
+        self.len(proto.chunk.code.len());
+        for &instruction in &proto.chunk.code {
+            self.instruction(instruction);
+        }
+        self.len(proto.chunk.constants.len());
+        for constant in &proto.chunk.constants {
+            self.value(constant)?;
+        }
+        self.len(proto.chunk.functions.len());
+        for function in &proto.chunk.functions {
+            match function.name {
+                Some(ref name) => {
+                    self.byte(1);
+                    self.string(name);
+                }
+                None => self.byte(0),
+            }
+            self.len(function.captures.len());
+            for &capture in &function.captures {
+                self.capture(capture);
+            }
+            self.len(function.clauses.len());
+            for clause in &function.clauses {
+                self.proto(clause)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn value(&mut self, value: &Value) -> Result<(), Error> {
+        match *value {
+            Value::Null => self.byte(0),
+            Value::Bool(boolean) => {
+                self.byte(1);
+                self.byte(boolean as u8);
+            }
+            Value::Integer(integer) => {
+                self.byte(2);
+                self.bytes.extend_from_slice(&integer.to_le_bytes());
+            }
+            Value::BigInteger(ref integer) => {
+                self.byte(8);
+                self.bigint(integer);
+            }
+            Value::Float(float) => {
+                self.byte(3);
+                self.bytes.extend_from_slice(&float.to_bits().to_le_bytes());
+            }
+            Value::Decimal(ref decimal) => {
+                self.byte(9);
+                self.len(decimal.scale() as usize);
+                self.bigint(decimal.digits());
+            }
+            Value::Rational(ref rational) => {
+                self.byte(10);
+                self.bigint(rational.numer());
+                self.bigint(rational.denom());
+            }
+            Value::String(ref string) => {
+                self.byte(4);
+                self.string(string);
+            }
+            Value::Bytes(ref bytes) => {
+                self.byte(11);
+                self.len(bytes.len());
+                self.bytes.extend_from_slice(bytes);
+            }
+            Value::Atom(ref atom) => {
+                self.byte(5);
+                self.string(atom.name());
+            }
+            Value::Array(ref items) => {
+                self.byte(6);
+                self.len(items.len());
+                for item in items {
+                    self.value(item)?;
+                }
+            }
+            Value::Map(ref map) => {
+                self.byte(7);
+                self.len(map.len());
+                for (key, value) in map {
+                    self.value(&key.to_value())?;
+                    self.value(value)?;
+                }
+            }
+            Value::Function(ref closure) => {
+                return Err(Error::UnexpectedFunction(closure.name().to_owned()))
+            }
+            Value::Type(ref ty) => return Err(Error::UnexpectedType(ty.def.name.clone())),
+            Value::Instance(ref instance) => {
+                return Err(Error::UnexpectedType(instance.borrow().ty.def.name.clone()))
+            }
+        }
+        Ok(())
+    }
+
+    /// An instruction or place code followed by its operands.
+    fn op(&mut self, code: u8, operands: &[usize]) {
+        self.byte(code);
+        for &operand in operands {
+            self.len(operand);
+        }
+    }
+
+    fn place(&mut self, place: Place) {
+        match place {
+            Place::Local(slot) => self.op(0, &[slot]),
+            Place::Upvalue(index) => self.op(1, &[index]),
+            Place::Global(global) => self.op(2, &[global]),
+            Place::Property(name) => self.op(3, &[name]),
+            Place::Itself => self.op(4, &[]),
+        }
+    }
+
+    fn capture(&mut self, capture: Capture) {
+        match capture {
+            Capture::Local(slot) => self.op(0, &[slot]),
+            Capture::Upvalue(index) => self.op(1, &[index]),
+            Capture::Itself => self.op(2, &[]),
+        }
+    }
+
+    fn instruction(&mut self, instruction: Instruction) {
+        use vm::chunk::Instruction::*;
+
+        match instruction {
+            Constant(index) => self.op(0, &[index]),
+            Null => self.op(1, &[]),
+            Pop => self.op(2, &[]),
+            Truncate(depth) => self.op(3, &[depth]),
+            Load(place) => {
+                self.byte(4);
+                self.place(place);
+            }
+            Store(place) => {
+                self.byte(5);
+                self.place(place);
+            }
+            SetItem(place, len) => {
+                self.byte(6);
+                self.place(place);
+                self.len(len);
+            }
+            Index => self.op(7, &[]),
+            Field(name) => self.op(8, &[name]),
+            Binary(op) => {
+                self.byte(9);
+                self.byte(code_of(&BINARY_OPS, &op));
+            }
+            Unary(op) => {
+                self.byte(10);
+                self.byte(code_of(&UNARY_OPS, &op));
+            }
+            Array(len) => self.op(11, &[len]),
+            Map(len) => self.op(12, &[len]),
+            Range => self.op(13, &[]),
+            TypeName(name) => self.op(14, &[name]),
+            Constructor(name, len) => self.op(15, &[name, len]),
+            PropertySet(len) => self.op(16, &[len]),
+            Closure(index) => self.op(17, &[index]),
+            Call(argc) => self.op(18, &[argc]),
+            CallGlobal(global, argc) => self.op(19, &[global, argc]),
+            Invoke(name, argc) => self.op(20, &[name, argc]),
+            InvokeSelf(name, argc) => self.op(21, &[name, argc]),
+            Tulis(len) => self.op(22, &[len]),
+            Jump(target) => self.op(23, &[target]),
+            JumpIfFalse(target) => self.op(24, &[target]),
+            Iterate(keyed) => self.op(25, &[keyed as usize]),
+            IterateRange => self.op(26, &[]),
+            Next(exit) => self.op(27, &[exit]),
+            EndIterate => self.op(28, &[]),
+            Return => self.op(29, &[]),
+            CallName(name, argc) => self.op(30, &[name, argc]),
+            Context(name) => self.op(31, &[name]),
+            Dot(len) => self.op(32, &[len]),
+            Subscript(len) => self.op(33, &[len]),
+            Falsy => self.op(34, &[]),
+            Folded(name, args, result) => self.op(35, &[name, args, result]),
+            JumpIfFalseKeep(target) => self.op(36, &[target]),
+            JumpIfTrueKeep(target) => self.op(37, &[target]),
+            ResetSlots(first, end) => self.op(38, &[first, end]),
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    /// Index in the running VM's `Globals` of each global of the file.
+    globals: Vec<usize>,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.bytes.len() - self.pos < len {
+            return Err(invalid("unexpected end of file"));
+        }
+        let bytes = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn flag(&mut self) -> Result<bool, Error> {
+        match self.byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(invalid("bad flag")),
+        }
+    }
+
+    fn len(&mut self) -> Result<usize, Error> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+    }
+
+    fn eight(&mut self) -> Result<[u8; 8], Error> {
+        let mut eight = [0; 8];
+        eight.copy_from_slice(self.take(8)?);
+        Ok(eight)
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        let len = self.len()?;
+        match String::from_utf8(self.take(len)?.to_vec()) {
+            Ok(string) => Ok(string),
+            Err(_) => Err(invalid("bad string")),
+        }
+    }
+
+    fn bigint(&mut self) -> Result<BigInt, Error> {
+        let len = self.len()?;
+        Ok(BigInt::from_signed_bytes_le(self.take(len)?))
+    }
+
+    fn strings(&mut self) -> Result<Vec<String>, Error> {
+        let len = self.len()?;
+        (0..len).map(|_| self.string()).collect()
+    }
+
+    fn table<T: Copy>(&mut self, table: &[T]) -> Result<T, Error> {
+        match table.get(self.byte()? as usize) {
+            Some(&item) => Ok(item),
+            None => Err(invalid("unknown operator")),
+        }
+    }
+
+    fn module(&mut self) -> Result<Module, Error> {
+        let len = self.len()?;
+        let mut traits = Vec::new();
+        for _ in 0..len {
+            let name = self.string()?;
+            let requires = self.strings()?;
+            let specs_len = self.len()?;
+            let specs = (0..specs_len).map(|_| self.spec()).collect::<Result<_, _>>()?;
+            let methods = self.methods()?;
+            traits.push(TraitProto {
+                name,
+                requires,
+                specs,
+                methods,
+            });
+        }
+        let len = self.len()?;
+        let mut types = Vec::new();
+        for _ in 0..len {
+            let name = self.string()?;
+            let props = self.strings()?;
+            let methods = self.methods()?;
+            let impls_len = self.len()?;
+            let mut impls = Vec::new();
+            for _ in 0..impls_len {
+                let trait_name = self.string()?;
+                let methods = self.methods()?;
+                impls.push(ImplProto { trait_name, methods });
+            }
+            types.push(TypeProto {
+                name,
+                props,
+                methods,
+                impls,
+            });
+        }
+        let main = Rc::new(self.proto(None)?);
+        Ok(Module { traits, types, main })
+    }
+
+    fn spec(&mut self) -> Result<Spec, Error> {
+        let kind = self.table(&METHOD_KINDS)?;
+        let name = self.string()?;
+        let arity = self.len()?;
+        let returns = if self.flag()? { Some(self.strings()?) } else { None };
+        Ok(Spec {
+            kind,
+            name,
+            arity,
+            returns,
+        })
+    }
+
+    fn methods(&mut self) -> Result<Vec<Method>, Error> {
+        let len = self.len()?;
+        let mut methods = Vec::new();
+        for _ in 0..len {
+            let kind = self.table(&METHOD_KINDS)?;
+            let name = self.string()?;
+            let predicate = self.flag()?;
+            let proto = Rc::new(self.proto(None)?);
+            methods.push(Method {
+                kind,
+                name,
+                predicate,
+                proto,
+            });
+        }
+        Ok(methods)
+    }
+
+    /// A proto, with the number of captured variables of the function it is a
+    /// clause of. Method bodies and the top level are not functions.
+    fn proto(&mut self, upvalues: Option<usize>) -> Result<Proto, Error> {
+        let arity = self.len()?;
+        let slots = self.len()?;
+        let len = self.len()?;
+        let code = (0..len).map(|_| self.instruction()).collect::<Result<Vec<_>, _>>()?;
+        let len = self.len()?;
+        let constants = (0..len).map(|_| self.value()).collect::<Result<_, _>>()?;
+        let len = self.len()?;
+        let mut functions = Vec::new();
+        for _ in 0..len {
+            let name = if self.flag()? { Some(self.string()?) } else { None };
+            let captures_len = self.len()?;
+            let captures = (0..captures_len).map(|_| self.capture()).collect::<Result<Vec<_>, _>>()?;
+            let captured = |capture: &Capture| match *capture {
+                Capture::Local(slot) => slot < slots,
+                Capture::Upvalue(index) => upvalues.is_some_and(|upvalues| index < upvalues),
+                Capture::Itself => upvalues.is_some(),
+            };
+            if !captures.iter().all(captured) {
+                return Err(invalid("capture out of range"));
+            }
+            let clauses_len = self.len()?;
+            let clauses = (0..clauses_len)
+                .map(|_| self.proto(Some(captures.len())).map(Rc::new))
+                .collect::<Result<_, _>>()?;
+            functions.push(Rc::new(FunctionProto { name, captures, clauses }));
+        }
+        // Every slot past the arguments is a local some instruction declares,
+        // which keeps a damaged count from allocating a huge frame.
+        if arity > slots || slots - arity > code.len() {
+            return Err(invalid("bad slot count"));
+        }
+        let chunk = Chunk {
+            code,
+            constants,
+            functions,
+        };
+        check_chunk(&chunk, slots, upvalues)?;
+        Ok(Proto { arity, slots, chunk })
+    }
+
+    fn value(&mut self) -> Result<Value, Error> {
+        Ok(match self.byte()? {
+            0 => Value::Null,
+            1 => Value::Bool(self.flag()?),
+            2 => Value::Integer(i64::from_le_bytes(self.eight()?)),
+            3 => Value::Float(f64::from_bits(u64::from_le_bytes(self.eight()?))),
+            4 => Value::String(self.string()?),
+            5 => Value::Atom(Atom::new(&self.string()?)),
+            6 => {
+                let len = self.len()?;
+                Value::Array((0..len).map(|_| self.value()).collect::<Result<_, _>>()?)
+            }
+            7 => {
+                let len = self.len()?;
+                let mut map = BTreeMap::new();
+                for _ in 0..len {
+                    let key = Key::from_value(&self.value()?)?;
+                    map.insert(key, self.value()?);
+                }
+                Value::Map(map)
+            }
+            8 => Value::from(self.bigint()?),
+            9 => {
+                let scale = self.len()? as u32;
+                Value::Decimal(Decimal::new(self.bigint()?, scale))
+            }
+            10 => {
+                let numerator = self.bigint()?;
+                let denominator = self.bigint()?;
+                if denominator.is_zero() {
+                    return Err(invalid("zero denominator"));
+                }
+                Value::Rational(BigRational::new(numerator, denominator))
+            }
+            11 => {
+                let len = self.len()?;
+                Value::Bytes(self.take(len)?.to_vec())
+            }
+            _ => return Err(invalid("unknown constant")),
+        })
+    }
+
+    fn global(&mut self) -> Result<usize, Error> {
+        let index = self.len()?;
+        match self.globals.get(index) {
+            Some(&global) => Ok(global),
+            None => Err(invalid("unknown global")),
+        }
+    }
+
+    fn place(&mut self) -> Result<Place, Error> {
+        Ok(match self.byte()? {
+            0 => Place::Local(self.len()?),
+            1 => Place::Upvalue(self.len()?),
+            2 => Place::Global(self.global()?),
+            3 => Place::Property(self.len()?),
+            4 => Place::Itself,
+            _ => return Err(invalid("unknown place")),
+        })
+    }
+
+    fn capture(&mut self) -> Result<Capture, Error> {
+        Ok(match self.byte()? {
+            0 => Capture::Local(self.len()?),
+            1 => Capture::Upvalue(self.len()?),
+            2 => Capture::Itself,
+            _ => return Err(invalid("unknown capture")),
+        })
+    }
+
+    fn instruction(&mut self) -> Result<Instruction, Error> {
+        use vm::chunk::Instruction::*;
+
+        Ok(match self.byte()? {
+            0 => Constant(self.len()?),
+            1 => Null,
+            2 => Pop,
+            3 => Truncate(self.len()?),
+            4 => Load(self.place()?),
+            5 => Store(self.place()?),
+            6 => SetItem(self.place()?, self.len()?),
+            7 => Index,
+            8 => Field(self.len()?),
+            9 => Binary(self.table(&BINARY_OPS)?),
+            10 => Unary(self.table(&UNARY_OPS)?),
+            11 => Array(self.len()?),
+            12 => Map(self.len()?),
+            13 => Range,
+            14 => TypeName(self.len()?),
+            15 => Constructor(self.len()?, self.len()?),
+            16 => PropertySet(self.len()?),
+            17 => Closure(self.len()?),
+            18 => Call(self.len()?),
+            19 => CallGlobal(self.global()?, self.len()?),
+            20 => Invoke(self.len()?, self.len()?),
+            21 => InvokeSelf(self.len()?, self.len()?),
+            22 => Tulis(self.len()?),
+            23 => Jump(self.len()?),
+            24 => JumpIfFalse(self.len()?),
+            25 => Iterate(self.len()? != 0),
+            26 => IterateRange,
+            27 => Next(self.len()?),
+            28 => EndIterate,
+            29 => Return,
+            30 => CallName(self.len()?, self.len()?),
+            31 => Context(self.len()?),
+            32 => Dot(self.len()?),
+            33 => Subscript(self.len()?),
+            34 => Falsy,
+            35 => Folded(self.len()?, self.len()?, self.len()?),
+            36 => JumpIfFalseKeep(self.len()?),
+            37 => JumpIfTrueKeep(self.len()?),
+            38 => ResetSlots(self.len()?, self.len()?),
+            _ => return Err(invalid("unknown instruction")),
+        })
+    }
+}
+
+/// Makes sure the operands of every instruction point inside the chunk and its
+/// frame, so a file that passes its checksum but was written wrongly is refused
+/// instead of crashing the VM.
+fn check_chunk(chunk: &Chunk, slots: usize, upvalues: Option<usize>) -> Result<(), Error> {
+    use vm::chunk::Instruction::*;
+
+    let is_name = |index: usize| matches!(chunk.constants.get(index), Some(Value::String(_)));
+    let place_ok = |place: Place| match place {
+        Place::Local(slot) => slot < slots,
+        Place::Property(name) => is_name(name),
+        Place::Upvalue(index) => upvalues.is_some_and(|upvalues| index < upvalues),
+        Place::Itself => upvalues.is_some(),
+        Place::Global(_) => true,
+    };
+    for &instruction in &chunk.code {
+        let ok = match instruction {
+            Constant(index) => index < chunk.constants.len(),
+            Load(place) => place_ok(place),
+            Store(place) | SetItem(place, _) => place != Place::Itself && place_ok(place),
+            Field(name) |
+            TypeName(name) |
+            Constructor(name, _) |
+            Invoke(name, _) |
+            InvokeSelf(name, _) |
+            CallName(name, _) |
+            Context(name) => is_name(name),
+            Folded(name, args, result) => {
+                is_name(name) && args < chunk.constants.len() && result < chunk.constants.len()
+            }
+            Closure(index) => index < chunk.functions.len(),
+            ResetSlots(first, end) => first <= end && end <= slots,
+            Jump(target) |
+            JumpIfFalse(target) |
+            JumpIfFalseKeep(target) |
+            JumpIfTrueKeep(target) |
+            Next(target) => target < chunk.code.len(),
+            _ => true,
+        };
+        if !ok {
+            return Err(invalid("operand out of range"));
+        }
+    }
+    check_stack(chunk)
+}
+
+/// Follows every path through the code, so each instruction finds the values
+/// it pops on the stack of its own call and `Next` only runs inside an
+/// `untuk`. Paths that meet have to agree on both, and none may run past the
+/// end of the code.
+fn check_stack(chunk: &Chunk) -> Result<(), Error> {
+    use vm::chunk::Instruction::*;
+
+    // The stack depth and the number of open loops each instruction starts with.
+    let mut seen: Vec<Option<(usize, usize)>> = vec![None; chunk.code.len()];
+    let mut pending = vec![(0, 0, 0)];
+    while let Some((ip, depth, loops)) = pending.pop() {
+        let instruction = match chunk.code.get(ip) {
+            Some(&instruction) => instruction,
+            None => return Err(invalid("code runs past its end")),
+        };
+        match seen[ip] {
+            Some(state) if state == (depth, loops) => continue,
+            Some(_) => return Err(invalid("paths disagree on the stack")),
+            None => seen[ip] = Some((depth, loops)),
+        }
+
+        let (pops, pushes) = match instruction {
+            Constant(_) | Null | Load(_) | TypeName(_) | Closure(_) => (0, 1),
+            Pop | Iterate(_) | JumpIfFalse(_) | Return => (1, 0),
+            Store(_) | Field(_) | Unary(_) => (1, 1),
+            SetItem(_, len) => (len + 1, 1),
+            Index | Binary(_) | Range => (2, 1),
+            IterateRange => (2, 0),
+            Array(len) | Tulis(len) | CallGlobal(_, len) | InvokeSelf(_, len) => (len, 1),
+            Map(len) | Constructor(_, len) | PropertySet(len) => (2 * len, 1),
+            Call(argc) | Invoke(_, argc) => (argc + 1, 1),
+            Truncate(_) | Jump(_) | Next(_) | EndIterate | ResetSlots(..) => (0, 0),
+            JumpIfFalseKeep(_) | JumpIfTrueKeep(_) => (1, 1),
+            // Only an `Expr` runs these, the VM refuses them.
+            CallName(..) | Context(_) | Dot(_) | Subscript(_) | Falsy | Folded(..) => continue,
+        };
+        if depth < pops {
+            return Err(invalid("stack underflow"));
+        }
+        let after = depth - pops + pushes;
+        match instruction {
+            Truncate(target) if target > depth => return Err(invalid("stack underflow")),
+            Truncate(target) => pending.push((ip + 1, target, loops)),
+            Jump(target) => pending.push((target, after, loops)),
+            JumpIfFalse(target) | JumpIfFalseKeep(target) | JumpIfTrueKeep(target) => {
+                pending.push((target, after, loops));
+                pending.push((ip + 1, after, loops));
+            }
+            Iterate(_) | IterateRange => pending.push((ip + 1, after, loops + 1)),
+            Next(_) | EndIterate if loops == 0 => return Err(invalid("loop instruction outside of a loop")),
+            Next(exit) => {
+                pending.push((exit, after, loops));
+                pending.push((ip + 1, after + 2, loops));
+            }
+            EndIterate => pending.push((ip + 1, after, loops - 1)),
+            Return => (),
+            _ => pending.push((ip + 1, after, loops)),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,9 @@
+pub mod chunk;
+pub mod compiler;
+pub mod inac;
+#[allow(clippy::module_inception)]
+pub mod vm;
+
+pub use self::chunk::*;
+pub use self::compiler::*;
+pub use self::vm::*;
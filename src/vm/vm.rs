@@ -0,0 +1,1033 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+use std::rc::Rc;
+use std::vec;
+use crate::*;
+use self::core::math::Math;
+use self::core::object::{Closure, Instance, Trait, Type};
+use self::core::tulis::tulis;
+use ast::{BinaryOp, MethodKind, UnaryOp};
+use builtin::BuiltIn;
+use value::Key;
+use vm::chunk::{Capture, Chunk, ImplProto, Instruction, Module, Place, Proto, TraitProto, TypeProto};
+use {Functions, Value};
+use Error;
+
+/// How many calls may run inside each other before a call fails with
+/// `Error::CallTooDeep`.
+pub const MAX_CALL_DEPTH: usize = 10000;
+
+/// A variable of a running call. Once a function created in the call captures
+/// it, it moves into a cell the two share.
+enum Slot {
+    Value(Value),
+    Cell(Rc<RefCell<Value>>),
+}
+
+/// The variables of one call, and the closure it runs for `Place::Upvalue` and
+/// `Place::Itself`.
+struct Frame {
+    slots: Vec<Slot>,
+    closure: Option<Rc<Closure>>,
+}
+
+impl Frame {
+    fn new(slots: usize, args: Vec<Value>, closure: Option<Rc<Closure>>) -> Frame {
+        let mut args = args.into_iter().map(Slot::Value).collect::<Vec<_>>();
+        args.resize_with(slots, || Slot::Value(Value::Null));
+        Frame { slots: args, closure }
+    }
+
+    fn get(&self, slot: usize) -> Value {
+        match self.slots[slot] {
+            Slot::Value(ref value) => value.clone(),
+            Slot::Cell(ref cell) => cell.borrow().clone(),
+        }
+    }
+
+    fn set(&mut self, slot: usize, value: Value) {
+        match self.slots[slot] {
+            Slot::Value(ref mut current) => *current = value,
+            Slot::Cell(ref cell) => *cell.borrow_mut() = value,
+        }
+    }
+
+    fn set_item(&mut self, slot: usize, path: &[Value], value: Value) -> Result<(), Error> {
+        match self.slots[slot] {
+            Slot::Value(ref mut current) => set_item(current, path, value),
+            Slot::Cell(ref cell) => set_item(&mut cell.borrow_mut(), path, value),
+        }
+    }
+
+    /// The cell of a slot a function being created captures.
+    fn cell(&mut self, slot: usize) -> Rc<RefCell<Value>> {
+        if let Slot::Value(ref mut value) = self.slots[slot] {
+            let value = std::mem::replace(value, Value::Null);
+            self.slots[slot] = Slot::Cell(Rc::new(RefCell::new(value)));
+        }
+        match self.slots[slot] {
+            Slot::Cell(ref cell) => cell.clone(),
+            Slot::Value(_) => unreachable!(),
+        }
+    }
+
+    /// Drops the cells of the slots in `first..end`, which functions made
+    /// before keep to themselves.
+    fn reset(&mut self, first: usize, end: usize) {
+        for slot in &mut self.slots[first..end] {
+            if let Slot::Cell(_) = *slot {
+                *slot = Slot::Value(Value::Null);
+            }
+        }
+    }
+
+    fn upvalue(&self, index: usize) -> &Rc<RefCell<Value>> {
+        &self.closure.as_ref().expect("upvalue outside of any function").upvalues[index]
+    }
+
+    fn itself(&self) -> Value {
+        Value::Function(self.closure.clone().expect("itself outside of any function"))
+    }
+}
+
+/// The method a call belongs to. `@` refers to its receiver, and only code of
+/// its type can call `defp` methods. Functions created in a method keep it.
+#[derive(Debug, Default)]
+pub struct MethodContext {
+    pub receiver: Option<Rc<RefCell<Instance>>>,
+    pub ty: Option<Rc<Type>>,
+}
+
+/// The keys and items an `untuk` still has to go through.
+enum Iteration {
+    Range { index: i64, next: i64, end: i64 },
+    Items(vec::IntoIter<(Value, Value)>),
+}
+
+impl Iteration {
+    /// Arrays and strings give their indexes as keys. Maps give their keys when
+    /// the loop binds them, and `[key, value]` items otherwise.
+    fn new(collection: Value, keyed: bool) -> Result<Iteration, Error> {
+        let items = match collection {
+            Value::Array(array) => {
+                array.into_iter().enumerate().map(|(index, item)| (Value::from(index), item)).collect()
+            }
+            Value::Map(map) => {
+                map.into_iter()
+                    .map(|(key, value)| if keyed {
+                        (key.to_value(), value)
+                    } else {
+                        (Value::Null, Value::Array(vec![key.to_value(), value]))
+                    })
+                    .collect()
+            }
+            Value::String(string) => {
+                string.chars()
+                    .enumerate()
+                    .map(|(index, c)| (Value::from(index), Value::String(c.to_string())))
+                    .collect::<Vec<_>>()
+            }
+            Value::Bytes(bytes) => {
+                bytes.into_iter()
+                    .enumerate()
+                    .map(|(index, byte)| (Value::from(index), Value::Integer(byte as i64)))
+                    .collect()
+            }
+            value => return Err(Error::NotIterable(value)),
+        };
+        Ok(Iteration::Items(items.into_iter()))
+    }
+}
+
+impl Iterator for Iteration {
+    type Item = (Value, Value);
+
+    fn next(&mut self) -> Option<(Value, Value)> {
+        match *self {
+            Iteration::Range { ref mut index, ref mut next, end } => {
+                if *next >= end {
+                    return None;
+                }
+                let item = (Value::Integer(*index), Value::Integer(*next));
+                *index += 1;
+                *next += 1;
+                Some(item)
+            }
+            Iteration::Items(ref mut items) => items.next(),
+        }
+    }
+}
+
+/// Variables of the top level. The compiler gives every global name an index,
+/// so running code finds a global without looking its name up.
+#[derive(Default)]
+pub struct Globals {
+    indexes: HashMap<String, usize>,
+    names: Vec<String>,
+    values: Vec<Option<Value>>,
+}
+
+impl Globals {
+    /// The index of `name`, given to it the first time it is asked for.
+    pub fn index(&mut self, name: &str) -> usize {
+        if let Some(&index) = self.indexes.get(name) {
+            return index;
+        }
+        let index = self.names.len();
+        self.indexes.insert(name.to_owned(), index);
+        self.names.push(name.to_owned());
+        self.values.push(None);
+        index
+    }
+
+    /// Whether the global has been given a value.
+    pub fn is_defined(&self, name: &str) -> bool {
+        match self.indexes.get(name) {
+            Some(&index) => self.values[index].is_some(),
+            None => false,
+        }
+    }
+
+    pub fn name(&self, index: usize) -> &str {
+        &self.names[index]
+    }
+
+    /// Every name given an index, in index order.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    fn get(&self, index: usize) -> Result<&Value, Error> {
+        match self.values[index] {
+            Some(ref value) => Ok(value),
+            None => Err(Error::VariableNotExists(self.names[index].clone())),
+        }
+    }
+
+    fn get_mut(&mut self, index: usize) -> Result<&mut Value, Error> {
+        match self.values[index] {
+            Some(ref mut value) => Ok(value),
+            None => Err(Error::VariableNotExists(self.names[index].clone())),
+        }
+    }
+}
+
+/// Stack machine running compiled BAIK programs. Globals, types and traits stay
+/// loaded from one run to the next.
+pub struct Vm {
+    globals: Globals,
+    types: HashMap<String, Rc<Type>>,
+    traits: HashMap<String, Rc<Trait>>,
+    builtin: Functions,
+    output: Box<dyn Write>,
+    stack: Vec<Value>,
+    /// How many calls are running, see `MAX_CALL_DEPTH`.
+    depth: usize,
+}
+
+impl Vm {
+    pub fn new() -> Vm {
+        Vm {
+            globals: Globals::default(),
+            types: HashMap::new(),
+            traits: HashMap::new(),
+            builtin: BuiltIn::new(),
+            output: Box::new(io::stdout()),
+            stack: Vec::new(),
+            depth: 0,
+        }
+    }
+
+    /// Creates a VM whose `tulis` output goes to `output` instead of stdout.
+    pub fn with_output<W: Write + 'static>(output: W) -> Vm {
+        Vm { output: Box::new(output), ..Vm::new() }
+    }
+
+    /// The globals programs have to be compiled against to run on this VM.
+    pub fn globals_mut(&mut self) -> &mut Globals {
+        &mut self.globals
+    }
+
+    /// Loads the definitions of `module`, traits first so every `impl` block can
+    /// be checked against its trait, then runs its top level.
+    pub fn run(&mut self, module: &Module) -> Result<Value, Error> {
+        for traitproto in &module.traits {
+            self.load_trait(traitproto);
+        }
+        for typeproto in &module.types {
+            self.load_type(typeproto)?;
+        }
+
+        self.stack.clear();
+        self.depth = 0;
+        let frame = Frame::new(module.main.slots, Vec::new(), None);
+        self.execute(&module.main, frame, &Rc::new(MethodContext::default()))
+    }
+
+    fn load_trait(&mut self, traitproto: &TraitProto) {
+        let tr = Trait { def: traitproto.clone() };
+        self.traits.insert(traitproto.name.clone(), Rc::new(tr));
+    }
+
+    fn load_type(&mut self, typeproto: &TypeProto) -> Result<(), Error> {
+        let mut traits = Vec::new();
+        for implproto in &typeproto.impls {
+            let tr = match self.traits.get(&implproto.trait_name) {
+                Some(tr) => tr.clone(),
+                None => return Err(Error::TraitNotExists(implproto.trait_name.clone())),
+            };
+            check_impl(&typeproto.name, implproto, &tr)?;
+            traits.push(tr);
+        }
+        for tr in &traits {
+            for required in &tr.def.requires {
+                if !traits.iter().any(|other| other.def.name == *required) {
+                    return Err(Error::MissingRequiredTrait(typeproto.name.clone(),
+                                                           tr.def.name.clone(),
+                                                           required.clone()));
+                }
+            }
+        }
+
+        let ty = Type {
+            def: typeproto.clone(),
+            traits,
+        };
+        self.types.insert(typeproto.name.clone(), Rc::new(ty));
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("pop from an empty stack")
+    }
+
+    /// The top `len` values, deepest first.
+    fn pop_many(&mut self, len: usize) -> Vec<Value> {
+        let at = self.stack.len() - len;
+        self.stack.split_off(at)
+    }
+
+    /// Runs one call of `proto` until it returns. Calls nest on the Rust stack,
+    /// which grows on the heap when it runs low.
+    fn execute(&mut self, proto: &Proto, frame: Frame, context: &Rc<MethodContext>) -> Result<Value, Error> {
+        if self.depth >= MAX_CALL_DEPTH {
+            return Err(Error::CallTooDeep(MAX_CALL_DEPTH));
+        }
+        self.depth += 1;
+        let result = stacker::maybe_grow(128 * 1024, 2 * 1024 * 1024, || {
+            self.execute_frame(proto, frame, context)
+        });
+        self.depth -= 1;
+        result
+    }
+
+    fn execute_frame(&mut self,
+                     proto: &Proto,
+                     mut frame: Frame,
+                     context: &Rc<MethodContext>)
+                     -> Result<Value, Error> {
+        let chunk = &proto.chunk;
+        let base = self.stack.len();
+        let mut iterations: Vec<Iteration> = Vec::new();
+        let mut ip = 0;
+        loop {
+            let instruction = chunk.code[ip];
+            ip += 1;
+            match instruction {
+                Instruction::Constant(index) => self.stack.push(chunk.constants[index].clone()),
+                Instruction::Null => self.stack.push(Value::Null),
+                Instruction::Pop => {
+                    self.pop();
+                }
+                Instruction::Truncate(depth) => self.stack.truncate(base + depth),
+                Instruction::Load(Place::Local(slot)) => {
+                    let value = frame.get(slot);
+                    self.stack.push(value);
+                }
+                Instruction::Load(place) => {
+                    let value = self.load(chunk, &frame, context, place)?;
+                    self.stack.push(value);
+                }
+                Instruction::Store(place) => {
+                    let value = self.stack.last().cloned().expect("store from an empty stack");
+                    match place {
+                        Place::Local(slot) => frame.set(slot, value),
+                        place => self.store(chunk, &mut frame, context, place, value)?,
+                    }
+                }
+                Instruction::SetItem(place, len) => {
+                    let value = self.pop();
+                    let path = self.pop_many(len);
+                    self.set_item(chunk, &mut frame, context, place, &path, value.clone())?;
+                    self.stack.push(value);
+                }
+                Instruction::Index => {
+                    let index = self.pop();
+                    let target = self.pop();
+                    self.stack.push(get_item(&target, &index)?);
+                }
+                Instruction::Field(name) => {
+                    let target = self.pop();
+                    let field = Value::String(chunk.name(name).to_owned());
+                    self.stack.push(get_item(&target, &field)?);
+                }
+                Instruction::Binary(op) => {
+                    let rhs = self.pop();
+                    let lhs = self.pop();
+                    let value = match (&lhs, &rhs) {
+                        (&Value::Integer(a), &Value::Integer(b)) => integer_operation(a, op, b),
+                        _ => None,
+                    };
+                    let value = match value {
+                        Some(value) => value,
+                        None => apply_operator(lhs, op, rhs)?,
+                    };
+                    self.stack.push(value);
+                }
+                Instruction::Unary(op) => {
+                    let value = self.pop();
+                    self.stack.push(apply_unary(op, value)?);
+                }
+                Instruction::Array(len) => {
+                    let items = self.pop_many(len);
+                    self.stack.push(Value::Array(items));
+                }
+                Instruction::Map(len) => {
+                    let items = self.pop_many(2 * len);
+                    self.stack.push(map_from_pairs(items)?);
+                }
+                Instruction::Range => {
+                    let end = self.pop();
+                    let start = self.pop();
+                    self.stack.push(range_array(&start, &end)?);
+                }
+                Instruction::TypeName(name) => {
+                    let ty = self.find_type(chunk.name(name))?;
+                    self.stack.push(Value::Type(ty));
+                }
+                Instruction::Constructor(name, len) => {
+                    let props = self.pop_many(2 * len);
+                    let instance = self.construct(chunk.name(name), props)?;
+                    self.stack.push(instance);
+                }
+                Instruction::PropertySet(len) => {
+                    let props = self.pop_many(2 * len);
+                    self.stack.push(set_properties(context, props)?);
+                }
+                Instruction::Closure(index) => {
+                    let function = chunk.functions[index].clone();
+                    let upvalues = function.captures
+                        .iter()
+                        .map(|&capture| match capture {
+                            Capture::Local(slot) => frame.cell(slot),
+                            Capture::Upvalue(index) => frame.upvalue(index).clone(),
+                            Capture::Itself => Rc::new(RefCell::new(frame.itself())),
+                        })
+                        .collect();
+                    let closure = Closure {
+                        function,
+                        upvalues,
+                        context: context.clone(),
+                    };
+                    self.stack.push(Value::Function(Rc::new(closure)));
+                }
+                Instruction::Call(argc) => {
+                    let args = self.pop_many(argc);
+                    let callee = self.pop();
+                    let value = self.call(&callee, args)?;
+                    self.stack.push(value);
+                }
+                Instruction::CallGlobal(global, argc) => {
+                    let args = self.pop_many(argc);
+                    let value = self.call_global(global, args)?;
+                    self.stack.push(value);
+                }
+                Instruction::Invoke(name, argc) => {
+                    let args = self.pop_many(argc);
+                    let receiver = self.pop();
+                    let value = self.call_method(&receiver, chunk.name(name), args, context)?;
+                    self.stack.push(value);
+                }
+                Instruction::InvokeSelf(name, argc) => {
+                    let args = self.pop_many(argc);
+                    let name = chunk.name(name);
+                    let receiver = Value::Instance(receiver(context, name)?);
+                    let value = self.call_method(&receiver, name, args, context)?;
+                    self.stack.push(value);
+                }
+                Instruction::Tulis(len) => {
+                    let values = self.pop_many(len);
+                    tulis(&mut self.output, &values).map_err(|e| Error::Custom(e.to_string()))?;
+                    self.stack.push(Value::Null);
+                }
+                Instruction::Jump(target) => ip = target,
+                Instruction::JumpIfFalseKeep(target) => {
+                    if let Some(Value::Bool(false)) = self.stack.last() {
+                        ip = target;
+                    }
+                }
+                Instruction::JumpIfTrueKeep(target) => {
+                    if let Some(Value::Bool(true)) = self.stack.last() {
+                        ip = target;
+                    }
+                }
+                Instruction::JumpIfFalse(target) => {
+                    match self.pop() {
+                        Value::Bool(true) => (),
+                        Value::Bool(false) => ip = target,
+                        value => return Err(Error::ExpectedBoolean(value)),
+                    }
+                }
+                Instruction::Iterate(keyed) => {
+                    let collection = self.pop();
+                    iterations.push(Iteration::new(collection, keyed)?);
+                }
+                Instruction::IterateRange => {
+                    let end = self.pop();
+                    let start = self.pop();
+                    let (start, end) = range(&start, &end)?;
+                    iterations.push(Iteration::Range {
+                        index: 0,
+                        next: start,
+                        end,
+                    });
+                }
+                Instruction::Next(exit) => {
+                    match iterations.last_mut().expect("next outside of untuk").next() {
+                        Some((key, item)) => {
+                            self.stack.push(key);
+                            self.stack.push(item);
+                        }
+                        None => ip = exit,
+                    }
+                }
+                Instruction::EndIterate => {
+                    iterations.pop();
+                }
+                Instruction::ResetSlots(first, end) => frame.reset(first, end),
+                Instruction::Return => {
+                    let value = self.pop();
+                    self.stack.truncate(base);
+                    return Ok(value);
+                }
+                Instruction::CallName(..) |
+                Instruction::Context(_) |
+                Instruction::Dot(_) |
+                Instruction::Subscript(_) |
+                Instruction::Falsy |
+                Instruction::Folded(..) => {
+                    return Err(Error::UnsupportedExpression(format!("{:?}", instruction)))
+                }
+            }
+        }
+    }
+
+    fn load(&self, chunk: &Chunk, frame: &Frame, context: &MethodContext, place: Place) -> Result<Value, Error> {
+        match place {
+            Place::Local(slot) => Ok(frame.get(slot)),
+            Place::Upvalue(index) => Ok(frame.upvalue(index).borrow().clone()),
+            Place::Itself => Ok(frame.itself()),
+            Place::Global(global) => self.globals.get(global).cloned(),
+            Place::Property(name) => {
+                let name = chunk.name(name);
+                let receiver = receiver(context, name)?;
+                let receiver = receiver.borrow();
+                match receiver.fields.get(name) {
+                    Some(value) => Ok(value.clone()),
+                    None => Err(Error::UnknownProperty(receiver.ty.def.name.clone(), name.to_owned())),
+                }
+            }
+        }
+    }
+
+    fn store(&mut self,
+             chunk: &Chunk,
+             frame: &mut Frame,
+             context: &MethodContext,
+             place: Place,
+             value: Value)
+             -> Result<(), Error> {
+        match place {
+            Place::Local(slot) => frame.set(slot, value),
+            Place::Upvalue(index) => *frame.upvalue(index).borrow_mut() = value,
+            Place::Itself => unreachable!("a function's own name is only read"),
+            Place::Global(global) => self.globals.values[global] = Some(value),
+            Place::Property(name) => {
+                let name = chunk.name(name);
+                let receiver = receiver(context, name)?;
+                let mut receiver = receiver.borrow_mut();
+                let typename = receiver.ty.def.name.clone();
+                match receiver.fields.get_mut(name) {
+                    Some(field) => *field = value,
+                    None => return Err(Error::UnknownProperty(typename, name.to_owned())),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Changes the item at the end of `path` inside the variable at `place`,
+    /// without copying the rest of the variable.
+    fn set_item(&mut self,
+                chunk: &Chunk,
+                frame: &mut Frame,
+                context: &MethodContext,
+                place: Place,
+                path: &[Value],
+                value: Value)
+                -> Result<(), Error> {
+        match place {
+            Place::Local(slot) => frame.set_item(slot, path, value),
+            Place::Upvalue(index) => set_item(&mut frame.upvalue(index).borrow_mut(), path, value),
+            Place::Itself => unreachable!("a function's own name is only read"),
+            Place::Global(global) => set_item(self.globals.get_mut(global)?, path, value),
+            Place::Property(name) => {
+                let name = chunk.name(name);
+                let receiver = receiver(context, name)?;
+                let mut instance = receiver.borrow_mut();
+                let typename = instance.ty.def.name.clone();
+                match instance.fields.get_mut(name) {
+                    Some(field) => set_item(field, path, value),
+                    None => Err(Error::UnknownProperty(typename, name.to_owned())),
+                }
+            }
+        }
+    }
+
+    fn find_type(&self, typename: &str) -> Result<Rc<Type>, Error> {
+        match self.types.get(typename) {
+            Some(ty) => Ok(ty.clone()),
+            None => Err(Error::TypeNotExists(typename.to_owned())),
+        }
+    }
+
+    /// `Tipe{ ... }` from property names and values, which have to be exactly
+    /// the properties of the type.
+    fn construct(&self, typename: &str, props: Vec<Value>) -> Result<Value, Error> {
+        let ty = self.find_type(typename)?;
+        let mut fields = BTreeMap::new();
+        for (property, value) in properties(props) {
+            if !ty.def.props.contains(&property) {
+                return Err(Error::UnknownProperty(typename.to_owned(), property));
+            }
+            fields.insert(property, value);
+        }
+        if let Some(missing) = ty.def.props.iter().find(|prop| !fields.contains_key(*prop)) {
+            return Err(Error::MissingProperty(typename.to_owned(), missing.clone()));
+        }
+
+        Ok(Value::Instance(Rc::new(RefCell::new(Instance { ty, fields }))))
+    }
+
+    /// Calls a global holding a function, falling back to the builtins when no
+    /// global of that name is defined.
+    fn call_global(&mut self, global: usize, args: Vec<Value>) -> Result<Value, Error> {
+        if let Some(ref callee) = self.globals.values[global] {
+            let callee = callee.clone();
+            return self.call(&callee, args);
+        }
+
+        let name = self.globals.name(global);
+        if let Some(&name) = HIGHER_ORDER.iter().find(|&&ident| ident == name) {
+            self.call_higher_order(name, args)
+        } else if let Some(function) = self.builtin.get(name) {
+            check_arguments(args.len(), function.min_args, function.max_args)?;
+            (function.compiled)(args)
+        } else {
+            Err(Error::FunctionNotExists(name.to_owned()))
+        }
+    }
+
+    /// Calls the clause of a function that takes as many arguments as given.
+    pub fn call(&mut self, callee: &Value, args: Vec<Value>) -> Result<Value, Error> {
+        let closure = match *callee {
+            Value::Function(ref closure) => closure,
+            ref callee => return Err(Error::NotCallable(callee.clone())),
+        };
+
+        let clause = match closure.function.clauses.iter().find(|clause| clause.arity == args.len()) {
+            Some(clause) => clause,
+            None => {
+                let arities = closure.function.clauses.iter().map(|clause| clause.arity);
+                return Err(wrong_arity(closure.name().to_owned(), args.len(), arities));
+            }
+        };
+        let frame = Frame::new(clause.slots, args, Some(closure.clone()));
+        self.execute(clause, frame, &closure.context)
+    }
+
+    /// Calls a method on an instance, or a `defs` method on a type like
+    /// `Titik.asal()`. `caller` is the method the call is made from.
+    fn call_method(&mut self,
+                   receiver: &Value,
+                   name: &str,
+                   args: Vec<Value>,
+                   caller: &MethodContext)
+                   -> Result<Value, Error> {
+        let (ty, instance) = match *receiver {
+            Value::Instance(ref instance) => (instance.borrow().ty.clone(), Some(instance.clone())),
+            Value::Type(ref ty) => (ty.clone(), None),
+            ref value => return Err(Error::MethodNotExists(value.type_name(), name.to_owned())),
+        };
+        let on_type = instance.is_none();
+        let typename = ty.def.name.clone();
+
+        let methods = ty.methods(name).collect::<Vec<_>>();
+        let candidates = methods.iter()
+            .filter(|method| (method.kind == MethodKind::Static) == on_type)
+            .collect::<Vec<_>>();
+        let method = match candidates.iter().find(|method| method.proto.arity == args.len()) {
+            Some(method) => method,
+            None if methods.is_empty() => {
+                return Err(Error::MethodNotExists(typename, name.to_owned()))
+            }
+            None if candidates.is_empty() && on_type => {
+                return Err(Error::InstanceMethodOnType(typename, name.to_owned()))
+            }
+            None if candidates.is_empty() => {
+                return Err(Error::StaticMethodOnInstance(typename, name.to_owned()))
+            }
+            None => {
+                let ident = format!("{}.{}", typename, name);
+                let arities = candidates.iter().map(|method| method.proto.arity);
+                return Err(wrong_arity(ident, args.len(), arities));
+            }
+        };
+        let inside_type = match caller.ty {
+            Some(ref current) => Rc::ptr_eq(current, &ty),
+            None => false,
+        };
+        if method.kind == MethodKind::Private && !inside_type {
+            return Err(Error::PrivateMethod(typename, name.to_owned()));
+        }
+
+        let context = Rc::new(MethodContext {
+            receiver: instance,
+            ty: Some(ty.clone()),
+        });
+        let frame = Frame::new(method.proto.slots, args, None);
+        let value = self.execute(&method.proto, frame, &context)?;
+
+        if method.predicate && !matches!(value, Value::Bool(_)) {
+            return Err(Error::ExpectedBoolean(value));
+        }
+        for (tr, spec) in ty.specs(method) {
+            if let Some(ref returns) = spec.returns {
+                if !returns.iter().all(|typename| value.is_a(typename)) {
+                    return Err(Error::TraitReturnType(tr.def.name.clone(),
+                                                      format!("{}.{}", typename, name),
+                                                      returns.join(" + "),
+                                                      value.type_name()));
+                }
+            }
+        }
+        Ok(value)
+    }
+
+    /// Calls `callee` and requires a boolean result, as callbacks for `saring`
+    /// and `urutkan` do.
+    fn call_predicate(&mut self, callee: &Value, args: Vec<Value>) -> Result<bool, Error> {
+        match self.call(callee, args)? {
+            Value::Bool(boolean) => Ok(boolean),
+            value => Err(Error::ExpectedBoolean(value)),
+        }
+    }
+
+    /// Builtins taking a callback. They live here rather than in `builtin`
+    /// because they have to run script functions.
+    ///
+    /// * `peta(xs, f)` gives `[f(x), ...]`.
+    /// * `saring(xs, f)` keeps the items for which `f(x)` is `benar`.
+    /// * `urutkan(xs)` sorts ascending, `urutkan(xs, f)` puts `a` before `b` when
+    ///   `f(a, b)` is `benar`. Both sorts are stable.
+    fn call_higher_order(&mut self, ident: &str, mut args: Vec<Value>) -> Result<Value, Error> {
+        let min_args = if ident == "urutkan" { 1 } else { 2 };
+        check_arguments(args.len(), Some(min_args), Some(2))?;
+        let callback = if args.len() == 2 { args.pop() } else { None };
+        let items = match args.pop() {
+            Some(Value::Array(items)) => items,
+            _ => return Err(Error::ExpectedArray),
+        };
+
+        match (ident, callback) {
+            ("peta", Some(callback)) => {
+                let mut mapped = Vec::new();
+                for item in items {
+                    mapped.push(self.call(&callback, vec![item])?);
+                }
+                Ok(Value::Array(mapped))
+            }
+            ("saring", Some(callback)) => {
+                let mut kept = Vec::new();
+                for item in items {
+                    if self.call_predicate(&callback, vec![item.clone()])? {
+                        kept.push(item);
+                    }
+                }
+                Ok(Value::Array(kept))
+            }
+            (_, Some(callback)) => {
+                let sorted = merge_sort(items, &mut |a: &Value, b: &Value| {
+                    self.call_predicate(&callback, vec![a.clone(), b.clone()])
+                })?;
+                Ok(Value::Array(sorted))
+            }
+            (_, None) => {
+                let sorted = merge_sort(items, &mut |a: &Value, b: &Value| {
+                    match apply_operator(a.clone(), BinaryOp::Lt, b.clone())? {
+                        Value::Bool(boolean) => Ok(boolean),
+                        value => Err(Error::ExpectedBoolean(value)),
+                    }
+                })?;
+                Ok(Value::Array(sorted))
+            }
+        }
+    }
+}
+
+impl Default for Vm {
+    fn default() -> Vm {
+        Vm::new()
+    }
+}
+
+/// Builtins implemented by `Vm::call_higher_order`.
+const HIGHER_ORDER: [&str; 3] = ["peta", "saring", "urutkan"];
+
+/// The instance whose method is running, needed by `@` expressions.
+fn receiver(context: &MethodContext, property: &str) -> Result<Rc<RefCell<Instance>>, Error> {
+    match context.receiver {
+        Some(ref instance) => Ok(instance.clone()),
+        None => Err(Error::PropertyOutsideMethod(property.to_owned())),
+    }
+}
+
+/// Pairs up property names and values pushed one after the other.
+fn properties(props: Vec<Value>) -> Vec<(String, Value)> {
+    let mut props = props.into_iter();
+    let mut pairs = Vec::new();
+    while let (Some(Value::String(property)), Some(value)) = (props.next(), props.next()) {
+        pairs.push((property, value));
+    }
+    pairs
+}
+
+/// `@{ ... }`, which changes properties of the receiver and gives it back.
+fn set_properties(context: &MethodContext, props: Vec<Value>) -> Result<Value, Error> {
+    let props = properties(props);
+    let first = props.first().map_or("", |prop| prop.0.as_str());
+    let receiver = receiver(context, first)?;
+    {
+        let mut instance = receiver.borrow_mut();
+        for (property, value) in props {
+            match instance.fields.get_mut(&property) {
+                Some(field) => *field = value,
+                None => {
+                    let typename = instance.ty.def.name.clone();
+                    return Err(Error::UnknownProperty(typename, property));
+                }
+            }
+        }
+    }
+    Ok(Value::Instance(receiver))
+}
+
+fn range(start: &Value, end: &Value) -> Result<(i64, i64), Error> {
+    match (start.as_i64(), end.as_i64()) {
+        (Some(start), Some(end)) => Ok((start, end)),
+        _ => Err(Error::InvalidRange(format!("{}..{}", start, end))),
+    }
+}
+
+/// `a..b` as an array, for a range that is not iterated by `untuk`.
+pub fn range_array(start: &Value, end: &Value) -> Result<Value, Error> {
+    let (start, end) = range(start, end)?;
+    Ok(Value::Array((start..end).map(Value::Integer).collect()))
+}
+
+/// A map from keys and values in turn.
+pub fn map_from_pairs(items: Vec<Value>) -> Result<Value, Error> {
+    let mut items = items.into_iter();
+    let mut map = BTreeMap::new();
+    while let (Some(key), Some(value)) = (items.next(), items.next()) {
+        map.insert(Key::from_value(&key)?, value);
+    }
+    Ok(Value::Map(map))
+}
+
+fn get_item(target: &Value, index: &Value) -> Result<Value, Error> {
+    match *target {
+        Value::Array(ref items) => Ok(items[array_index(index, items.len())?].clone()),
+        Value::String(ref string) => {
+            let chars = string.chars().collect::<Vec<_>>();
+            Ok(Value::String(chars[array_index(index, chars.len())?].to_string()))
+        }
+        Value::Bytes(ref bytes) => Ok(Value::Integer(bytes[array_index(index, bytes.len())?] as i64)),
+        Value::Map(ref map) => Ok(map.get(&Key::from_value(index)?).cloned().unwrap_or(Value::Null)),
+        ref value => Err(Error::NotIndexable(value.type_name())),
+    }
+}
+
+/// Sets the item at the end of `path`. Arrays can't grow this way, but maps get
+/// a new entry for a new key.
+fn set_item(target: &mut Value, path: &[Value], value: Value) -> Result<(), Error> {
+    let (index, rest) = path.split_first().unwrap();
+    let slot = match *target {
+        Value::Array(ref mut items) => {
+            let index = array_index(index, items.len())?;
+            &mut items[index]
+        }
+        Value::Map(ref mut map) => {
+            let key = Key::from_value(index)?;
+            if rest.is_empty() {
+                map.insert(key, value);
+                return Ok(());
+            }
+            match map.get_mut(&key) {
+                Some(slot) => slot,
+                None => return Err(Error::NotIndexable(Value::Null.type_name())),
+            }
+        }
+        ref value => return Err(Error::NotIndexable(value.type_name())),
+    };
+
+    if rest.is_empty() {
+        *slot = value;
+        Ok(())
+    } else {
+        set_item(slot, rest, value)
+    }
+}
+
+fn array_index(index: &Value, len: usize) -> Result<usize, Error> {
+    match index.as_i64() {
+        Some(index) if index >= 0 && (index as usize) < len => Ok(index as usize),
+        Some(index) => Err(Error::IndexOutOfRange(index, len)),
+        None => Err(Error::ExpectedNumber),
+    }
+}
+
+/// Every method the trait specifies has to be in the `impl` block, as the same
+/// kind of method and with the same number of arguments.
+fn check_impl(typename: &str, implproto: &ImplProto, tr: &Trait) -> Result<(), Error> {
+    for spec in &tr.def.specs {
+        let is_static = spec.kind == MethodKind::Static;
+        let candidates = implproto.methods
+            .iter()
+            .filter(|method| method.name == spec.name && (method.kind == MethodKind::Static) == is_static)
+            .collect::<Vec<_>>();
+        if candidates.iter().any(|method| method.proto.arity == spec.arity) {
+            continue;
+        }
+        return Err(match candidates.first() {
+            Some(method) => {
+                Error::TraitMethodArity(typename.to_owned(),
+                                        tr.def.name.clone(),
+                                        spec.name.clone(),
+                                        spec.arity,
+                                        method.proto.arity)
+            }
+            None => {
+                Error::MissingTraitMethod(typename.to_owned(), tr.def.name.clone(), spec.name.clone())
+            }
+        });
+    }
+    Ok(())
+}
+
+fn wrong_arity<I: Iterator<Item = usize>>(ident: String, given: usize, arities: I) -> Error {
+    let accepted = arities.map(|arity| arity.to_string()).collect::<Vec<_>>();
+    Error::WrongArity(ident, given, accepted.join(", "))
+}
+
+pub fn check_arguments(len: usize, min: Option<usize>, max: Option<usize>) -> Result<(), Error> {
+    if let Some(max) = max {
+        if len > max {
+            return Err(Error::ArgumentsGreater(max));
+        }
+    }
+    if let Some(min) = min {
+        if len < min {
+            return Err(Error::ArgumentsLess(min));
+        }
+    }
+    Ok(())
+}
+
+/// Stable merge sort with a fallible `less`, which a script comparator is.
+fn merge_sort<F>(mut items: Vec<Value>, less: &mut F) -> Result<Vec<Value>, Error>
+    where F: FnMut(&Value, &Value) -> Result<bool, Error>
+{
+    if items.len() < 2 {
+        return Ok(items);
+    }
+
+    let right = items.split_off(items.len() / 2);
+    let mut left = merge_sort(items, less)?.into_iter().peekable();
+    let mut right = merge_sort(right, less)?.into_iter().peekable();
+    let mut merged = Vec::new();
+    loop {
+        let from_right = match (left.peek(), right.peek()) {
+            (Some(a), Some(b)) => less(b, a)?,
+            (Some(_), None) => false,
+            (None, Some(_)) => true,
+            (None, None) => return Ok(merged),
+        };
+        merged.extend(if from_right { right.next() } else { left.next() });
+    }
+}
+
+/// Operations on two integers that need no error handling, done without going
+/// through `Math`. An overflow goes through `Math` too, to report it.
+fn integer_operation(a: i64, op: BinaryOp, b: i64) -> Option<Value> {
+    Some(match op {
+        BinaryOp::Add => Value::Integer(a.checked_add(b)?),
+        BinaryOp::Sub => Value::Integer(a.checked_sub(b)?),
+        BinaryOp::Mul => Value::Integer(a.checked_mul(b)?),
+        BinaryOp::Eq => Value::Bool(a == b),
+        BinaryOp::Ne => Value::Bool(a != b),
+        BinaryOp::Gt => Value::Bool(a > b),
+        BinaryOp::Lt => Value::Bool(a < b),
+        BinaryOp::Ge => Value::Bool(a >= b),
+        BinaryOp::Le => Value::Bool(a <= b),
+        _ => return None,
+    })
+}
+
+/// `==` and `!=` work on any values, see `Value::eq`. Everything else goes
+/// through `Math`.
+pub fn apply_operator(lhs: Value, op: BinaryOp, rhs: Value) -> Result<Value, Error> {
+    match op {
+        BinaryOp::Eq => Ok(Value::Bool(lhs == rhs)),
+        BinaryOp::Ne => Ok(Value::Bool(lhs != rhs)),
+        BinaryOp::Add => lhs.add(&rhs),
+        BinaryOp::Sub => lhs.sub(&rhs),
+        BinaryOp::Mul => lhs.mul(&rhs),
+        BinaryOp::Div => lhs.div(&rhs),
+        BinaryOp::IntDiv => lhs.int_div(&rhs),
+        BinaryOp::Rem => lhs.rem(&rhs),
+        BinaryOp::Pow => lhs.pow(&rhs),
+        BinaryOp::Shl => lhs.shl(&rhs),
+        BinaryOp::Shr => lhs.shr(&rhs),
+        BinaryOp::BitAnd => lhs.bitand(&rhs),
+        BinaryOp::BitOr => lhs.bitor(&rhs),
+        BinaryOp::BitXor => lhs.bitxor(&rhs),
+        BinaryOp::Gt => lhs.gt(&rhs),
+        BinaryOp::Lt => lhs.lt(&rhs),
+        BinaryOp::Ge => lhs.ge(&rhs),
+        BinaryOp::Le => lhs.le(&rhs),
+        BinaryOp::And => lhs.and(&rhs),
+        BinaryOp::Or => lhs.or(&rhs),
+    }
+}
+
+pub fn apply_unary(op: UnaryOp, value: Value) -> Result<Value, Error> {
+    match (op, value) {
+        (UnaryOp::Neg, value) => value.neg(),
+        (UnaryOp::Plus, value) if value.is_number() => Ok(value),
+        (UnaryOp::Plus, _) => Err(Error::ExpectedNumber),
+        (UnaryOp::Not, Value::Bool(boolean)) => Ok(Value::Bool(!boolean)),
+        (UnaryOp::Not, value) => Err(Error::ExpectedBoolean(value)),
+    }
+}
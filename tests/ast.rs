@@ -0,0 +1,209 @@
+#[cfg(test)]
+extern crate baik;
+mod tests {
+    use baik::Error;
+    use baik::ast::*;
+
+    fn first_expr(program: Program) -> Expr {
+        match program.stmts.into_iter().next() {
+            Some(Stmt::Expr(expr)) => expr,
+            stmt => panic!("expected expression, found: {:?}", stmt),
+        }
+    }
+
+    #[test]
+    fn test_precedence() {
+        let expr = first_expr(parse("1 + 2 * 3").unwrap());
+        match expr.kind {
+            ExprKind::Binary(BinaryOp::Add, lhs, rhs) => {
+                assert_eq!(lhs.kind, ExprKind::Integer(1));
+                match rhs.kind {
+                    ExprKind::Binary(BinaryOp::Mul, _, _) => (),
+                    kind => panic!("expected multiplication, found: {:?}", kind),
+                }
+            }
+            kind => panic!("expected addition, found: {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn test_call_chain() {
+        let expr = first_expr(parse("fs[0](1)()").unwrap());
+        match expr.kind {
+            ExprKind::Call(callee, args) => {
+                assert!(args.is_empty());
+                match callee.kind {
+                    ExprKind::Call(callee, args) => {
+                        assert_eq!(args.len(), 1);
+                        match callee.kind {
+                            ExprKind::Index(..) => (),
+                            kind => panic!("expected index, found: {:?}", kind),
+                        }
+                    }
+                    kind => panic!("expected call, found: {:?}", kind),
+                }
+            }
+            kind => panic!("expected call, found: {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn test_spans() {
+        let program = parse("a = 1\ntulis a").unwrap();
+        match program.stmts[1] {
+            Stmt::Expr(ref expr) => {
+                assert_eq!(expr.span.start, 6);
+                assert_eq!(expr.span.end, 13);
+                assert_eq!((expr.span.line, expr.span.column), (2, 1));
+                match expr.kind {
+                    ExprKind::Tulis(ref args) => assert_eq!(args[0].span.column, 7),
+                    ref kind => panic!("expected tulis, found: {:?}", kind),
+                }
+            }
+            ref stmt => panic!("expected expression, found: {:?}", stmt),
+        }
+    }
+
+    #[test]
+    fn test_function() {
+        let expr = first_expr(parse("fungsi f(a) { a }, (a, b) { a + b }").unwrap());
+        match expr.kind {
+            ExprKind::Function(function) => {
+                assert_eq!(function.name, Some("f".to_owned()));
+                assert_eq!(function.clauses.len(), 2);
+                assert_eq!(function.clauses[1].args, vec!["a", "b"]);
+            }
+            kind => panic!("expected function, found: {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn test_anonymous_function() {
+        let expr = first_expr(parse("kali = fungsi (x) { x * 2 }").unwrap());
+        match expr.kind {
+            ExprKind::Declaration(ref ident, ref value) => {
+                assert_eq!(ident, "kali");
+                match value.kind {
+                    ExprKind::Function(ref function) => {
+                        assert_eq!(function.name, None);
+                        assert_eq!(function.clauses[0].args, vec!["x"]);
+                    }
+                    ref kind => panic!("expected function, found: {:?}", kind),
+                }
+            }
+            kind => panic!("expected declaration, found: {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn test_assignment_target() {
+        let expr = first_expr(parse("a[0].b = 1").unwrap());
+        match expr.kind {
+            ExprKind::Assign(ref target, _) => {
+                match target.kind {
+                    ExprKind::Field(ref inner, ref field) => {
+                        assert_eq!(field, "b");
+                        match inner.kind {
+                            ExprKind::Index(ref array, _) => {
+                                assert_eq!(array.kind, ExprKind::Local("a".to_owned()))
+                            }
+                            ref kind => panic!("expected index, found: {:?}", kind),
+                        }
+                    }
+                    ref kind => panic!("expected field, found: {:?}", kind),
+                }
+            }
+            kind => panic!("expected assignment, found: {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn test_method_chain() {
+        let expr = first_expr(parse("a.b(1).c()").unwrap());
+        match expr.kind {
+            ExprKind::MethodCall(receiver, method, args) => {
+                assert_eq!(method, "c");
+                assert!(args.is_empty());
+                match receiver.kind {
+                    ExprKind::MethodCall(_, ref method, ref args) => {
+                        assert_eq!(method, "b");
+                        assert_eq!(args.len(), 1);
+                    }
+                    ref kind => panic!("expected method call, found: {:?}", kind),
+                }
+            }
+            kind => panic!("expected method call, found: {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn test_definitions() {
+        let source = "
+            trait Bentuk maka
+                def luas(): Angka
+                defs kosong?()
+            selesai
+
+            type Titik(x, y) maka
+                def geser(dx) maka @{ x: @x + dx } selesai
+                defp rahasia() maka 1 selesai
+                impl Bentuk maka
+                    def luas() maka 0 selesai
+                selesai
+            selesai
+        ";
+        let program = parse_file(source).unwrap();
+        match program.stmts[0] {
+            Stmt::Trait(ref traitdef) => {
+                assert_eq!(traitdef.name, "Bentuk");
+                assert_eq!(traitdef.specs.len(), 2);
+                assert_eq!(traitdef.specs[0].returns, Some(vec!["Angka".to_owned()]));
+                assert_eq!(traitdef.specs[1].kind, MethodKind::Static);
+                assert!(traitdef.specs[1].predicate);
+            }
+            ref stmt => panic!("expected trait, found: {:?}", stmt),
+        }
+        match program.stmts[1] {
+            Stmt::Type(ref typedef) => {
+                assert_eq!(typedef.props, vec!["x", "y"]);
+                assert_eq!(typedef.methods.len(), 2);
+                assert_eq!(typedef.methods[1].kind, MethodKind::Private);
+                assert_eq!(typedef.impls[0].trait_name, "Bentuk");
+            }
+            ref stmt => panic!("expected type, found: {:?}", stmt),
+        }
+    }
+
+    #[test]
+    fn test_nested_brackets() {
+        // Each level used to be parsed several times over.
+        let source = format!("{}1{}", "(".repeat(12), ")".repeat(12));
+        assert_eq!(parse_expression(&source).unwrap().kind, ExprKind::Integer(1));
+    }
+
+    #[test]
+    fn test_range_and_assignment_operands() {
+        match parse_expression("a[0]..1.5").unwrap().kind {
+            ExprKind::Range(start, end) => {
+                match start.kind {
+                    ExprKind::Index(..) => (),
+                    kind => panic!("expected index, found: {:?}", kind),
+                }
+                assert_eq!(end.kind, ExprKind::Float(1.5));
+            }
+            kind => panic!("expected range, found: {:?}", kind),
+        }
+        match parse_expression("a[0] == 1").unwrap().kind {
+            ExprKind::Binary(BinaryOp::Eq, _, _) => (),
+            kind => panic!("expected comparison, found: {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn test_syntax_error() {
+        match parse("a = ") {
+            Err(Error::Syntax(_)) => (),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+}
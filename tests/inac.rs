@@ -0,0 +1,120 @@
+#[cfg(test)]
+extern crate baik;
+mod tests {
+    use baik::*;
+    use baik::core::Interpreter;
+    use baik::vm::Globals;
+    use baik::vm::inac::{self, FORMAT_VERSION};
+
+    const SCRIPT: &str = r#"
+        trait Bentuk maka
+            def luas(): Angka
+            def ringkas() maka [:luas, @luas()] selesai
+        selesai
+        type Persegi(sisi) maka
+            impl Bentuk maka def luas() maka @sisi * @sisi selesai selesai
+        selesai
+        fungsi jumlah(xs) { total = 0
+            untuk x dalam xs { total = total + x }
+            total }
+        skala = 2.5
+        [jumlah([1, 2, 3]), Persegi{ sisi: 3 }.ringkas(), { "a" => skala }, 2 ** 64, -123456789012345678901234567890, 12.50d / 4, pecahan(2, 6), Bita("hi")]
+    "#;
+
+    fn compiled() -> Vec<u8> {
+        Interpreter::new().compile_inac(SCRIPT).unwrap()
+    }
+
+    /// Writes the checksum of a changed file again, so it gets past that check.
+    fn resealed(mut bytes: Vec<u8>) -> Vec<u8> {
+        let mut crc = !0u32;
+        for &byte in &bytes[10..] {
+            crc ^= u32::from(byte);
+            for _ in 0..8 {
+                crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            }
+        }
+        bytes[6..10].copy_from_slice(&(!crc).to_le_bytes());
+        bytes
+    }
+
+    /// Changes the operand of the first `instruction` followed by `operand`.
+    fn with_operand(bytes: &[u8], instruction: u8, operand: u32, changed: u32) -> Vec<u8> {
+        let mut pattern = vec![instruction];
+        pattern.extend_from_slice(&operand.to_le_bytes());
+        let at = bytes.windows(5).position(|window| window == &pattern[..]).unwrap();
+        let mut bytes = bytes.to_vec();
+        bytes[at + 1..at + 5].copy_from_slice(&changed.to_le_bytes());
+        resealed(bytes)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let bytes = compiled();
+        assert!(inac::is_inac(&bytes));
+        let expected = Interpreter::new().run(SCRIPT);
+        assert!(expected.is_ok(), "{:?}", expected);
+        assert_eq!(Interpreter::new().run_inac(&bytes), expected);
+    }
+
+    #[test]
+    fn test_globals_of_earlier_runs() {
+        let bytes = Interpreter::new().compile_inac("fungsi tambah(a) { a + n }\ntambah(1)").unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.run("x = 1\ny = 2\nn = 10").unwrap();
+        assert_eq!(interpreter.run_inac(&bytes), Ok(to_value(11)));
+        assert_eq!(interpreter.run("tambah(y)"), Ok(to_value(12)));
+    }
+
+    #[test]
+    fn test_error_version() {
+        let mut bytes = compiled();
+        bytes[4] = 99;
+        assert_eq!(Interpreter::new().run_inac(&bytes),
+                   Err(Error::BytecodeVersion(99, FORMAT_VERSION)));
+    }
+
+    #[test]
+    fn test_error_damaged() {
+        let mut bytes = compiled();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        assert_eq!(Interpreter::new().run_inac(&bytes),
+                   Err(Error::InvalidBytecode("checksum mismatch".to_owned())));
+        assert_eq!(Interpreter::new().run_inac(&compiled()[..20]),
+                   Err(Error::InvalidBytecode("checksum mismatch".to_owned())));
+        assert_eq!(Interpreter::new().run_inac(b"tulis 1"),
+                   Err(Error::InvalidBytecode("not a compiled BAIK program".to_owned())));
+    }
+
+    #[test]
+    fn test_error_bad_operands() {
+        let bytes = Interpreter::new().compile_inac("a = [1, 2]\ntulis a[0], 3").unwrap();
+        assert_eq!(Interpreter::new().run_inac(&resealed(bytes.clone())), Ok(Value::Null));
+        // `Tulis` and `Array` popping more values than there are, then a jump past the end.
+        let damaged = [with_operand(&bytes, 22, 2, 3), with_operand(&bytes, 11, 2, 3)];
+        for bytes in &damaged {
+            match Interpreter::new().run_inac(bytes) {
+                Err(Error::InvalidBytecode(_)) => (),
+                result => panic!("expected invalid bytecode, found {:?}", result),
+            }
+        }
+        let bytes = Interpreter::new().compile_inac("jika benar { 1 } lainnya { 2 }").unwrap();
+        match Interpreter::new().run_inac(&with_operand(&bytes, 24, 4, 99)) {
+            Err(Error::InvalidBytecode(_)) => (),
+            result => panic!("expected invalid bytecode, found {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_damaged_files_do_not_panic() {
+        let bytes = compiled();
+        for at in 10..bytes.len() {
+            for &change in &[1, 0x80, 0xff] {
+                let mut damaged = bytes.clone();
+                damaged[at] = damaged[at].wrapping_add(change);
+                let _ = inac::decode(&resealed(damaged), &mut Globals::default());
+            }
+        }
+    }
+}
@@ -0,0 +1,867 @@
+#[cfg(test)]
+extern crate baik;
+mod tests {
+    use baik::*;
+    use baik::core::{interpreter, Interpreter};
+    use std::cell::RefCell;
+    use std::io::{self, Write};
+    use std::rc::Rc;
+
+    #[derive(Clone, Default)]
+    struct Output(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn output_of(script: &str) -> String {
+        let output = Output::default();
+        Interpreter::with_output(output.clone()).run(script).unwrap();
+        let bytes = output.0.borrow().clone();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn test_last_value() {
+        assert_eq!(interpreter("1 + 2 * (1 + 2)".to_owned()), Ok(to_value(7)));
+    }
+
+    #[test]
+    fn test_declaration() {
+        assert_eq!(interpreter("a = 2\nb = a * 3\nb + 1".to_owned()), Ok(to_value(7)));
+    }
+
+    #[test]
+    fn test_literals() {
+        assert_eq!(interpreter("0x1f + 0o7 + 0b11".to_owned()), Ok(to_value(41)));
+        assert_eq!(interpreter("1.5 + 1".to_owned()), Ok(to_value(2.5)));
+        assert_eq!(interpreter(r#""ha" + "lo""#.to_owned()), Ok(to_value("halo")));
+        assert_eq!(interpreter("benar && salah".to_owned()), Ok(to_value(false)));
+        assert_eq!(interpreter("[1, 2, 3]".to_owned()), Ok(to_value(vec![1, 2, 3])));
+    }
+
+    #[test]
+    fn test_unary() {
+        assert_eq!(interpreter("-5 + 2".to_owned()), Ok(to_value(-3)));
+        assert_eq!(interpreter("!(1 == 2)".to_owned()), Ok(to_value(true)));
+        assert_eq!(interpreter("-benar".to_owned()), Err(Error::ExpectedNumber));
+    }
+
+    #[test]
+    fn test_negation() {
+        assert_eq!(interpreter("-2 ** 2".to_owned()), Ok(to_value(-4)));
+        assert_eq!(interpreter("x = 2\n[-x ** 2, (-x) ** 2, x ** -1, -x ** 3 ** 2]".to_owned()),
+                   Ok(to_value(vec![to_value(-4), to_value(4), to_value(0.5), to_value(-512)])));
+        assert_eq!(interpreter("x = -9223372036854775807 - 1\ny = -x\ny".to_owned()),
+                   Ok(Value::from(BigInt::from(1) << 63)));
+        assert_eq!(interpreter("x = 2 ** 64\ny = -x\ny".to_owned()),
+                   Ok(Value::from(-(BigInt::from(1) << 64usize))));
+        match interpreter("x = 0.0\ny = -x\ny".to_owned()) {
+            Ok(Value::Float(zero)) => assert!(zero == 0.0 && zero.is_sign_negative()),
+            value => panic!("expected a float, found {:?}", value),
+        }
+        assert_eq!(output_of("x = 12.50d\ny = pecahan(1, 3)\ntulis -x, \" \", -y"), "-12.50 -1/3");
+    }
+
+    #[test]
+    fn test_power_and_bitwise() {
+        assert_eq!(interpreter("2 ** 3 ** 2".to_owned()), Ok(to_value(512)));
+        assert_eq!(interpreter("x = 3\n(1 << x) | (x & 1) ^ 7".to_owned()), Ok(to_value(14)));
+    }
+
+    #[test]
+    fn test_numeric_tower() {
+        assert_eq!(interpreter("x = 7\n[x / 2, x // 2, x % 2]".to_owned()),
+                   Ok(to_value(vec![to_value(3.5), to_value(3), to_value(1)])));
+        assert_eq!(interpreter("x = 0\n10 // x".to_owned()), Err(Error::DivisionByZero));
+        assert_eq!(interpreter("x = -9223372036854775807 - 1\ny = -x\ny - x".to_owned()),
+                   Ok(Value::from(BigInt::from(1) << 64)));
+    }
+
+    #[test]
+    fn test_big_integer_factorial() {
+        let script = "fungsi faktorial(n) { jika (n < 2) { kembali 1 }\nn * faktorial(n - 1) }\n\
+                      tulis faktorial(30), \" \", jenis(faktorial(30)), \" \", faktorial(30) // faktorial(28)";
+        assert_eq!(output_of(script), "265252859812191058636308480000000 BilanganBesar 870");
+    }
+
+    #[test]
+    fn test_decimal_billing() {
+        let script = "harga = [12.50d, 7.25d, Desimal(\"0.10\")]\n\
+                      total = 0\n\
+                      untuk h dalam harga { total = total + h }\n\
+                      pajak = bulatkan(total * 0.11d, 2, :setengah_atas)\n\
+                      tulis total, \" \", pajak, \" \", [total + pajak]";
+        assert_eq!(output_of(script), "19.85 2.18 [22.03d]");
+    }
+
+    #[test]
+    fn test_bytes() {
+        let script = "b = Bita(\"abc\")\n\
+                      kode = { b => \"teks\" }\n\
+                      untuk x dalam b { tulis x, \" \" }\n\
+                      tulis b[1], \" \", panjang(b), \" \", kode[Bita([97, 98, 99])], \" \", b";
+        assert_eq!(output_of(script), "97 98 99 98 3 teks Bita([97, 98, 99])");
+    }
+
+    #[test]
+    fn test_rational_lesson() {
+        let script = "sepertiga = pecahan(1, 3)\n\
+                      jumlah = 0\n\
+                      untuk i dalam [1, 2, 3] { jumlah = jumlah + sepertiga }\n\
+                      tulis sepertiga, \" \", pecahan(3, 12) + pecahan(1, 6), \" \", jumlah, \" \", jenis(jumlah)";
+        assert_eq!(output_of(script), "1/3 5/12 1 Pecahan");
+    }
+
+    #[test]
+    fn test_builtin_call() {
+        assert_eq!(interpreter("max(3, 9, 4) - min(3, 9, 4)".to_owned()), Ok(to_value(6)));
+    }
+
+    #[test]
+    fn test_function_call() {
+        let script = "fungsi tambah(a, b) { a + b }\ntambah(2, 3) * 2";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(10)));
+    }
+
+    #[test]
+    fn test_if_expression() {
+        let script = "a = 3\nb = \"\"\njika (a > 1) { b = \"besar\" } lainnya { b = \"kecil\" }\nb";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value("besar")));
+    }
+
+    #[test]
+    fn test_error_variable_not_exists() {
+        assert_eq!(interpreter("a + 1".to_owned()),
+                   Err(Error::VariableNotExists("a".to_owned())));
+    }
+
+    #[test]
+    fn test_error_syntax() {
+        match interpreter("a = ".to_owned()) {
+            Err(Error::Syntax(_)) => (),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_assignment_updates_outer_binding() {
+        let script = "a = 1\njika (benar) { a = 2 }\na";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(2)));
+    }
+
+    #[test]
+    fn test_let_shadows_outer_binding() {
+        let script = "a = 1\njika (benar) { let a = 2\na = a + 1 }\na";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(1)));
+    }
+
+    #[test]
+    fn test_block_bindings_are_local() {
+        let script = "jika (benar) { b = 2 }\nb";
+        assert_eq!(interpreter(script.to_owned()),
+                   Err(Error::VariableNotExists("b".to_owned())));
+    }
+
+    #[test]
+    fn test_nested_scopes() {
+        let script = "
+            total = 0
+            fungsi tambah(n) {
+                jika (n > 0) {
+                    jika (n > 1) { total = total + n }
+                    total = total + 1
+                }
+                total
+            }
+            tambah(2)
+            tambah(5)
+        ";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(9)));
+    }
+
+    #[test]
+    fn test_function_clause_by_arity() {
+        let script = "
+            fungsi luas(sisi) { sisi * sisi }, (panjang, lebar) { panjang * lebar }
+            luas(3) + luas(2, 5)
+        ";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(19)));
+    }
+
+    #[test]
+    fn test_function_returns_last_value() {
+        let script = "fungsi f() { a = 1\nb = a + 1\nb * 10 }\nf()";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(20)));
+    }
+
+    #[test]
+    fn test_error_wrong_arity() {
+        let script = "fungsi f(a) { a }, (a, b) { b }\nf(1, 2, 3)";
+        assert_eq!(interpreter(script.to_owned()),
+                   Err(Error::WrongArity("f".to_owned(), 3, "1, 2".to_owned())));
+    }
+
+    #[test]
+    fn test_else_if_chain() {
+        let script = "
+            fungsi nilai(n) {
+                jika n >= 90 {
+                    \"A\"
+                } jikatidak n >= 80 {
+                    \"B\"
+                } jika tidak (n >= 70) {
+                    \"C\"
+                } jikatidak n >= 60 {
+                    \"D\"
+                } lainnya {
+                    \"E\"
+                }
+            }
+            nilai(95) + nilai(85) + nilai(75) + nilai(65) + nilai(10)
+        ";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value("ABCDE")));
+    }
+
+    #[test]
+    fn test_if_value() {
+        let script = "a = jika 1 > 2 { 1 } lainnya { 2 }\nb = jika salah { 3 }\n[a, b]";
+        assert_eq!(interpreter(script.to_owned()),
+                   Ok(to_value(vec![to_value(2), Value::Null])));
+    }
+
+    #[test]
+    fn test_and_or_short_circuit() {
+        let script = "a = [1]\npanjang(a) > 5 && a[5] == 1";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(false)));
+        let script = "a = [1]\npanjang(a) == 1 || a[9] == 1";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(true)));
+        let script = "n = 0\nfungsi naik() { n = n + 1\nbenar }\nsalah && naik()\nbenar || naik()\nn";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(0)));
+    }
+
+    #[test]
+    fn test_error_if_expected_boolean() {
+        let script = "jika salah { 1 } jikatidak 5 { 2 }";
+        assert_eq!(interpreter(script.to_owned()), Err(Error::ExpectedBoolean(to_value(5))));
+    }
+
+    #[test]
+    fn test_tulis_format() {
+        assert_eq!(output_of(r#"tulis "ya", 123, "ya", (1 + 2)"#), "ya123ya3");
+        assert_eq!(output_of("tulis 4 - 6, \" \", 0.5, \" \", 1.5 * 2"), "-2 0.5 3.0");
+        assert_eq!(output_of("tulis 0.1 + 0.2"), "0.3");
+        assert_eq!(output_of("tulis benar, salah"), "benarsalah");
+    }
+
+    #[test]
+    fn test_tulis_collections() {
+        assert_eq!(output_of(r#"tulis [1, "dua", [3.5]]"#), r#"[1, "dua", [3.5]]"#);
+        assert_eq!(output_of(r#"tulis { nama: "Budi", "umur anak" => 7 }"#),
+                   r#"{nama: "Budi", "umur anak" => 7}"#);
+    }
+
+    #[test]
+    fn test_tulis_escapes() {
+        assert_eq!(output_of(r#"tulis "a\tb\n", "\"kutip\" \\ \u0041""#),
+                   "a\tb\n\"kutip\" \\ A");
+    }
+
+    #[test]
+    fn test_while_loop() {
+        let script = "i = 0\ntotal = 0\nselama i < 5 { i = i + 1\ntotal = total + i }\ntotal";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(15)));
+    }
+
+    #[test]
+    fn test_for_range() {
+        let script = "total = 0\nuntuk i dalam 1..5 { total = total + i }\ntotal";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(10)));
+        assert_eq!(interpreter("n = 3\n0..n".to_owned()), Ok(to_value(vec![0, 1, 2])));
+    }
+
+    #[test]
+    fn test_for_array_and_string() {
+        assert_eq!(output_of("untuk x dalam [1, 2, 3] { tulis x * 2, \" \" }"), "2 4 6 ");
+        assert_eq!(output_of("untuk i, x dalam [\"a\", \"b\"] { tulis i, x }"), "0a1b");
+        assert_eq!(output_of("untuk c dalam \"baik\" { tulis c, \".\" }"), "b.a.i.k.");
+    }
+
+    #[test]
+    fn test_for_map() {
+        assert_eq!(output_of("untuk k, v dalam { a: 1, b: 2 } { tulis k, \"=\", v, \";\" }"),
+                   "a=1;b=2;");
+        assert_eq!(output_of("untuk e dalam { a: 1 } { tulis e }"), r#"["a", 1]"#);
+    }
+
+    #[test]
+    fn test_for_variable_is_local() {
+        let script = "untuk x dalam [1] { x }\nx";
+        assert_eq!(interpreter(script.to_owned()),
+                   Err(Error::VariableNotExists("x".to_owned())));
+    }
+
+    #[test]
+    fn test_error_not_iterable() {
+        assert_eq!(interpreter("untuk x dalam 5 { x }".to_owned()),
+                   Err(Error::NotIterable(to_value(5))));
+    }
+
+    #[test]
+    fn test_return() {
+        let script = "fungsi cari(x) { jika x > 2 { kembali \"besar\" }\n\"kecil\" }\n\
+                      cari(1) + cari(5)";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value("kecilbesar")));
+        assert_eq!(interpreter("fungsi kosong() { kembali }\nkosong()".to_owned()),
+                   Ok(Value::Null));
+    }
+
+    #[test]
+    fn test_bare_return_ends_at_line() {
+        assert_eq!(output_of("fungsi f() { kembali\ntulis \"tidak\" }\nf()"), "");
+        assert_eq!(output_of("fungsi f() { kembali # selesai\ntulis \"tidak\" }\nf()"), "");
+        assert_eq!(interpreter("fungsi f() { kembali (1 +\n2) }\nf()".to_owned()), Ok(to_value(3)));
+    }
+
+    #[test]
+    fn test_return_from_loop() {
+        let script = "fungsi pertama(xs) { untuk x dalam xs { jika x > 1 { kembali x } }\n0 }\n\
+                      pertama([1, 5, 7])";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(5)));
+    }
+
+    #[test]
+    fn test_break_and_continue() {
+        assert_eq!(output_of("untuk i dalam 0..10 { jika i == 3 { berhenti }\ntulis i }"),
+                   "012");
+        assert_eq!(output_of("untuk i dalam 0..5 { jika i % 2 == 0 { lanjut }\ntulis i }"),
+                   "13");
+        let script = "i = 0\nselama benar { i = i + 1\njika i < 3 { lanjut }\nberhenti }\ni";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(3)));
+    }
+
+    #[test]
+    fn test_error_control_flow_outside_context() {
+        assert_eq!(interpreter("kembali 1".to_owned()), Err(Error::ReturnOutsideFunction(1)));
+        assert_eq!(interpreter("a = 1\nberhenti".to_owned()), Err(Error::BreakOutsideLoop(2)));
+        assert_eq!(interpreter("lanjut".to_owned()), Err(Error::ContinueOutsideLoop(1)));
+        let script = "untuk i dalam 0..2 { fungsi f() { berhenti } }";
+        assert_eq!(interpreter(script.to_owned()), Err(Error::BreakOutsideLoop(1)));
+    }
+
+    #[test]
+    fn test_anonymous_function() {
+        let script = "kali = fungsi (x) { x * 2 }\nkali(21)";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(42)));
+        assert_eq!(output_of("tulis fungsi (x) { x }, \" \", fungsi f() { 1 }"),
+                   "<fungsi anonim> <fungsi f>");
+    }
+
+    #[test]
+    fn test_function_as_value() {
+        let script = "fungsi dua_kali(f, x) { f(f(x)) }\n\
+                      fungsi tambah(x) { x + 3 }\n\
+                      g = tambah\n\
+                      dua_kali(g, 1)";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(7)));
+    }
+
+    #[test]
+    fn test_closure_captures_scope() {
+        let script = "fungsi penambah(n) { fungsi (x) { x + n } }\n\
+                      tambah_lima = penambah(5)\n\
+                      tambah_lima(3)";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(8)));
+
+        let script = "hitung = 0\n\
+                      fungsi naik() { hitung = hitung + 1 }\n\
+                      naik()\nnaik()\nhitung";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(2)));
+
+        let script = "fungsi pencacah() { let n = 0\nfungsi () { n = n + 1 } }\n\
+                      c = pencacah()\nc()\nc()";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(2)));
+    }
+
+    #[test]
+    fn test_call_chains() {
+        let script = "fungsi tambah(a) { fungsi (b) { a + b } }\n\
+                      fs = [fungsi (x) { x * 10 }, tambah]\n\
+                      m = { f: fungsi () { [7, 8] } }\n\
+                      [fs[0](4), fs[1](2)(3), m[\"f\"]()[1], (fungsi (x) { x })(5)]";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(vec![40, 5, 8, 5])));
+    }
+
+    #[test]
+    fn test_closures_capture_each_iteration() {
+        let script = "fs = [0, 0, 0]\n\
+                      untuk i dalam 0..3 { let j = i\nfs[i] = fungsi () { j } }\n\
+                      a = fs[0]\nb = fs[2]\ntulis a(), b()";
+        assert_eq!(output_of(script), "02");
+        let script = "fs = [0, 0]\nuntuk i dalam 0..2 { fs[i] = fungsi () { i } }\na = fs[0]\na()";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(0)));
+        let script = "n = 0\nfs = [0, 0]\n\
+                      selama n < 2 { let k = n * 10\nfs[n] = fungsi () { k }\nn = n + 1 }\n\
+                      a = fs[0]\na()";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(0)));
+    }
+
+    #[test]
+    fn test_recursion() {
+        let script = "fungsi fak(n) { jika n == 0 { 1 } lainnya { n * fak(n - 1) } }\nfak(5)";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(120)));
+    }
+
+    #[test]
+    fn test_higher_order_builtins() {
+        assert_eq!(interpreter("peta([1, 2, 3], fungsi (x) { x * x })".to_owned()),
+                   Ok(to_value(vec![1, 4, 9])));
+        assert_eq!(interpreter("saring(0..6, fungsi (x) { x % 2 == 0 })".to_owned()),
+                   Ok(to_value(vec![0, 2, 4])));
+        assert_eq!(interpreter("urutkan([3, 1, 2])".to_owned()),
+                   Ok(to_value(vec![1, 2, 3])));
+        assert_eq!(interpreter("urutkan([3, 1, 2], fungsi (a, b) { a > b })".to_owned()),
+                   Ok(to_value(vec![3, 2, 1])));
+        let script = r#"urutkan(["ccc", "a", "bb", "d"], fungsi (a, b) { panjang(a) < panjang(b) })"#;
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(vec!["a", "d", "bb", "ccc"])));
+    }
+
+    #[test]
+    fn test_error_not_callable() {
+        assert_eq!(interpreter("a = 1\na(2)".to_owned()),
+                   Err(Error::NotCallable(to_value(1))));
+        assert_eq!(interpreter("f = fungsi (x) { x }\nf + 1".to_owned()),
+                   Err(Error::UnsupportedTypes("<fungsi anonim>".to_owned(), "1".to_owned())));
+        assert_eq!(interpreter("saring([1], fungsi (x) { x })".to_owned()),
+                   Err(Error::ExpectedBoolean(to_value(1))));
+    }
+
+    const TITIK: &str = "
+        type Titik(x, y) maka
+            def geser(dx, dy) maka @{ x: @x + dx, y: @y + dy } selesai
+            def jumlah() maka @x + @y selesai
+            def jumlah(n) maka @jumlah() * n selesai
+        selesai
+    ";
+
+    fn run_with_titik(script: &str) -> Result<Value, Error> {
+        interpreter(format!("{}\n{}", TITIK, script))
+    }
+
+    #[test]
+    fn test_constructor_and_methods() {
+        assert_eq!(run_with_titik("p = Titik{ x: 1, y: 2 }\np.jumlah()"), Ok(to_value(3)));
+        assert_eq!(run_with_titik("p = Titik{ x: 1, y: 2 }\np.geser(2, 3)\np.jumlah(10)"),
+                   Ok(to_value(80)));
+        assert_eq!(run_with_titik("Titik{ x: 1, y: 2 }.geser(1, 1).jumlah()"), Ok(to_value(5)));
+    }
+
+    #[test]
+    fn test_instance_is_shared() {
+        let script = "p = Titik{ x: 1, y: 2 }\nq = p\nq.geser(1, 0)\ntulis p, \" \", p == q, \" \",
+                      p == Titik{ x: 2, y: 2 }";
+        let output = output_of(&format!("{}\n{}", TITIK, script));
+        assert_eq!(output, "Titik{x: 2, y: 2} benar salah");
+    }
+
+    #[test]
+    fn test_type_used_before_definition() {
+        let script = "Kotak{ sisi: 3 }.luas()\n\
+                      type Kotak(sisi) maka def luas() maka @sisi * @sisi selesai selesai";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(9)));
+    }
+
+    #[test]
+    fn test_error_constructor() {
+        assert_eq!(run_with_titik("Titik{ x: 1, y: 2, z: 3 }"),
+                   Err(Error::UnknownProperty("Titik".to_owned(), "z".to_owned())));
+        assert_eq!(run_with_titik("Titik{ x: 1 }"),
+                   Err(Error::MissingProperty("Titik".to_owned(), "y".to_owned())));
+        assert_eq!(interpreter("Garis{}".to_owned()),
+                   Err(Error::TypeNotExists("Garis".to_owned())));
+    }
+
+    #[test]
+    fn test_error_methods_and_properties() {
+        assert_eq!(run_with_titik("Titik{ x: 1, y: 2 }.putar()"),
+                   Err(Error::MethodNotExists("Titik".to_owned(), "putar".to_owned())));
+        assert_eq!(run_with_titik("Titik{ x: 1, y: 2 }.geser(1)"),
+                   Err(Error::WrongArity("Titik.geser".to_owned(), 1, "2".to_owned())));
+        assert_eq!(interpreter("a = [1]\na.jumlah()".to_owned()),
+                   Err(Error::MethodNotExists("Larik".to_owned(), "jumlah".to_owned())));
+        assert_eq!(interpreter("a = @x".to_owned()),
+                   Err(Error::PropertyOutsideMethod("x".to_owned())));
+    }
+
+    const BENTUK: &str = "
+        trait Nama maka
+            def nama(): Teks
+        selesai
+
+        trait Bentuk : Nama maka
+            def luas(): Angka
+            def keliling(): Angka
+            def ringkas() maka [@nama(), @luas()] selesai
+        selesai
+    ";
+
+    fn run_with_bentuk(script: &str) -> Result<Value, Error> {
+        interpreter(format!("{}\n{}", BENTUK, script))
+    }
+
+    #[test]
+    fn test_trait_methods() {
+        let script = "
+            type Persegi(sisi) maka
+                def keliling() maka 4 * @sisi selesai
+                impl Nama maka
+                    def nama() maka \"persegi\" selesai
+                selesai
+                impl Bentuk maka
+                    def luas() maka @sisi * @sisi selesai
+                    def keliling() maka 0 selesai
+                selesai
+            selesai
+            p = Persegi{ sisi: 3 }
+            [p.luas(), p.keliling(), p.nama()]
+        ";
+        assert_eq!(run_with_bentuk(script), Ok(to_value(vec![to_value(9), to_value(12),
+                                                              to_value("persegi")])));
+    }
+
+    #[test]
+    fn test_trait_default_method() {
+        let script = "
+            type Persegi(sisi) maka
+                impl Nama maka def nama() maka \"persegi\" selesai selesai
+                impl Bentuk maka
+                    def luas() maka @sisi * @sisi selesai
+                    def keliling() maka 4 * @sisi selesai
+                selesai
+            selesai
+            Persegi{ sisi: 3 }.ringkas()
+        ";
+        assert_eq!(run_with_bentuk(script), Ok(to_value(vec![to_value("persegi"), to_value(9)])));
+    }
+
+    #[test]
+    fn test_error_impl_checked_at_load_time() {
+        let missing = "
+            tulis \"tidak jalan\"
+            type Persegi(sisi) maka
+                impl Nama maka selesai
+            selesai
+        ";
+        assert_eq!(run_with_bentuk(missing),
+                   Err(Error::MissingTraitMethod("Persegi".to_owned(),
+                                                 "Nama".to_owned(),
+                                                 "nama".to_owned())));
+
+        let arity = "
+            type Persegi(sisi) maka
+                impl Nama maka def nama(x) maka x selesai selesai
+            selesai
+        ";
+        assert_eq!(run_with_bentuk(arity),
+                   Err(Error::TraitMethodArity("Persegi".to_owned(),
+                                               "Nama".to_owned(),
+                                               "nama".to_owned(),
+                                               0,
+                                               1)));
+
+        let required = "
+            type Persegi(sisi) maka
+                impl Bentuk maka
+                    def luas() maka 1 selesai
+                    def keliling() maka 1 selesai
+                selesai
+            selesai
+        ";
+        assert_eq!(run_with_bentuk(required),
+                   Err(Error::MissingRequiredTrait("Persegi".to_owned(),
+                                                   "Bentuk".to_owned(),
+                                                   "Nama".to_owned())));
+
+        assert_eq!(interpreter("type A maka impl B maka selesai selesai".to_owned()),
+                   Err(Error::TraitNotExists("B".to_owned())));
+    }
+
+    const AKUN: &str = "
+        type Akun(saldo) maka
+            defs baru() maka Akun{ saldo: Akun.awal() } selesai
+            defs awal() maka 10 selesai
+            defp pajak(n) maka n % 7 selesai
+            def tarik(n) maka @{ saldo: @saldo - n - @pajak(n) } selesai
+            def pajak_dari(akun) maka akun.pajak(@saldo) selesai
+            def lihat() maka @saldo selesai
+        selesai
+    ";
+
+    fn run_with_akun(script: &str) -> Result<Value, Error> {
+        interpreter(format!("{}\n{}", AKUN, script))
+    }
+
+    #[test]
+    fn test_static_method() {
+        assert_eq!(run_with_akun("Akun.baru().lihat()"), Ok(to_value(10)));
+        assert_eq!(run_with_akun("t = Akun\nt.awal()"), Ok(to_value(10)));
+        assert_eq!(output_of(&format!("{}\ntulis Akun, \" \", Akun == Akun", AKUN)), "Akun benar");
+    }
+
+    #[test]
+    fn test_private_method_inside_type() {
+        assert_eq!(run_with_akun("Akun{ saldo: 100 }.tarik(50).lihat()"), Ok(to_value(49)));
+        assert_eq!(run_with_akun("a = Akun{ saldo: 20 }\nAkun{ saldo: 30 }.pajak_dari(a)"),
+                   Ok(to_value(2)));
+    }
+
+    #[test]
+    fn test_error_method_kinds() {
+        assert_eq!(run_with_akun("Akun{ saldo: 1 }.pajak(10)"),
+                   Err(Error::PrivateMethod("Akun".to_owned(), "pajak".to_owned())));
+        assert_eq!(run_with_akun("f = fungsi (a) { a.pajak(1) }\nf(Akun.baru())"),
+                   Err(Error::PrivateMethod("Akun".to_owned(), "pajak".to_owned())));
+        assert_eq!(run_with_akun("Akun.baru().awal()"),
+                   Err(Error::StaticMethodOnInstance("Akun".to_owned(), "awal".to_owned())));
+        assert_eq!(run_with_akun("Akun.lihat()"),
+                   Err(Error::InstanceMethodOnType("Akun".to_owned(), "lihat".to_owned())));
+        assert_eq!(run_with_akun("Akun + 1"),
+                   Err(Error::UnsupportedTypes("Akun".to_owned(), "1".to_owned())));
+    }
+
+    #[test]
+    fn test_predicate_method() {
+        let script = "
+            type Kotak(isi) maka
+                def kosong?() maka @isi == 0 selesai
+                def penuh?() maka @isi selesai
+                def ada?() maka !@kosong?() selesai
+            selesai
+            k = Kotak{ isi: 0 }
+        ";
+        assert_eq!(interpreter(format!("{}\nk.kosong?()", script)), Ok(to_value(true)));
+        assert_eq!(interpreter(format!("{}\nk.ada?()", script)), Ok(to_value(false)));
+        assert_eq!(interpreter(format!("{}\nk.penuh?()", script)),
+                   Err(Error::ExpectedBoolean(to_value(0))));
+    }
+
+    #[test]
+    fn test_trait_return_type() {
+        let script = "
+            trait Ukur maka
+                def ukuran(): Angka
+                def salinan(): Ukur
+            selesai
+            type Tali(panjang) maka
+                impl Ukur maka
+                    def ukuran() maka @panjang selesai
+                    def salinan() maka Tali{ panjang: @panjang } selesai
+                selesai
+            selesai
+            type Rusak maka
+                impl Ukur maka
+                    def ukuran() maka \"panjang\" selesai
+                    def salinan() maka 1 selesai
+                selesai
+            selesai
+        ";
+        assert_eq!(interpreter(format!("{}\nTali{{ panjang: 3 }}.salinan().ukuran()", script)),
+                   Ok(to_value(3)));
+        assert_eq!(interpreter(format!("{}\nRusak{{}}.ukuran()", script)),
+                   Err(Error::TraitReturnType("Ukur".to_owned(),
+                                              "Rusak.ukuran".to_owned(),
+                                              "Angka".to_owned(),
+                                              "Teks".to_owned())));
+        assert_eq!(interpreter(format!("{}\nRusak{{}}.salinan()", script)),
+                   Err(Error::TraitReturnType("Ukur".to_owned(),
+                                              "Rusak.salinan".to_owned(),
+                                              "Ukur".to_owned(),
+                                              "Angka".to_owned())));
+    }
+
+    #[test]
+    fn test_atoms() {
+        assert_eq!(output_of("status = :selesai\ntulis status, \" \", [:a, \":a\"]"),
+                   r#":selesai [:a, ":a"]"#);
+        assert_eq!(interpreter(":ok == :ok".to_owned()), Ok(to_value(true)));
+        assert_eq!(interpreter(":ok == :gagal".to_owned()), Ok(to_value(false)));
+        assert_eq!(interpreter(":ok == \"ok\"".to_owned()), Ok(to_value(false)));
+        assert_eq!(interpreter(":ok + 1".to_owned()),
+                   Err(Error::UnsupportedTypes(":ok".to_owned(), "1".to_owned())));
+    }
+
+    #[test]
+    fn test_atom_map_keys() {
+        let script = r#"m = { :ok => 1, "ok" => 2, nama: 3 }
+                        untuk k, v dalam m { tulis k, "=", v, ";" }
+                        tulis " ", m"#;
+        assert_eq!(output_of(script), r#"nama=3;ok=2;:ok=1; {nama: 3, ok: 2, :ok => 1}"#);
+    }
+
+    #[test]
+    fn test_jenis() {
+        let script = r#"type Titik(x) maka selesai
+                        [jenis(:a), jenis("a"), jenis(1), jenis(1.5), jenis([]), jenis({}),
+                         jenis(fungsi () { 1 }), jenis(Titik{ x: 1 }), jenis(benar)]"#;
+        assert_eq!(interpreter(script.to_owned()),
+                   Ok(to_value(vec!["Atom", "Teks", "Angka", "Angka", "Larik", "Peta", "Fungsi",
+                                    "Titik", "Boolean"])));
+    }
+
+    #[test]
+    fn test_index_read() {
+        assert_eq!(interpreter("a = [1, [2, 3]]\na[1][0] + a[0]".to_owned()), Ok(to_value(3)));
+        assert_eq!(interpreter(r#"m = { a: { b: 5 } }
+                                  [m.a.b, m["a"]["b"], m.c]"#.to_owned()),
+                   Ok(to_value(vec![to_value(5), to_value(5), Value::Null])));
+        assert_eq!(interpreter("s = \"baik\"\ns[1]".to_owned()), Ok(to_value("a")));
+        assert_eq!(interpreter("[10, 20][1]".to_owned()), Ok(to_value(20)));
+    }
+
+    #[test]
+    fn test_index_assignment() {
+        let script = "a = [1, 2, 3]\nb = a\na[0] = 9\nuntuk i dalam 1..3 { a[i] = a[i] * 10 }\n[a, b]";
+        assert_eq!(interpreter(script.to_owned()),
+                   Ok(to_value(vec![vec![9, 20, 30], vec![1, 2, 3]])));
+
+        let script = r#"m = { a: { b: 1 } }
+                        m["c"] = 2
+                        m.a.b = 3
+                        m[:d] = [0]
+                        m[:d][0] = 4
+                        tulis m"#;
+        assert_eq!(output_of(script), "{a: {b: 3}, c: 2, :d => [4]}");
+    }
+
+    #[test]
+    fn test_index_assignment_in_method() {
+        let script = "
+            type Tumpukan(isi) maka
+                def ganti(i, x) maka @isi[i] = x selesai
+                def lihat() maka @isi selesai
+            selesai
+            t = Tumpukan{ isi: [1, 2] }
+            t.ganti(1, 5)
+            t.lihat()
+        ";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(vec![1, 5])));
+    }
+
+    #[test]
+    fn test_instance_holding_itself() {
+        let script = "
+            type Simpul(isi, berikut) maka
+                def sambung(simpul) maka @{ berikut: simpul } selesai
+            selesai
+            a = Simpul{ isi: 1, berikut: 0 }
+            b = Simpul{ isi: 2, berikut: 0 }
+            a.sambung(a)
+            b.sambung(a)
+        ";
+        assert_eq!(output_of(&format!("{}\ntulis a", script)), "Simpul{isi: 1, berikut: <siklus>}");
+        // Only an instance inside itself is cut short, not one seen twice.
+        let b = "Simpul{isi: 2, berikut: Simpul{isi: 1, berikut: <siklus>}}";
+        assert_eq!(output_of(&format!("{}\ntulis [b, b]", script)), format!("[{}, {}]", b, b));
+        let a = interpreter(format!("{}\na", script)).unwrap();
+        assert_eq!(a.to_json(), Err(Error::CyclicValue("Simpul".to_owned())));
+    }
+
+    #[test]
+    fn test_error_index() {
+        assert_eq!(interpreter("a = [1, 2]\na[2] = 3".to_owned()),
+                   Err(Error::IndexOutOfRange(2, 2)));
+        assert_eq!(interpreter("a = [1, 2]\na[-1]".to_owned()),
+                   Err(Error::IndexOutOfRange(-1, 2)));
+        assert_eq!(interpreter("a = 1\na[0] = 3".to_owned()),
+                   Err(Error::NotIndexable("Angka".to_owned())));
+        assert_eq!(interpreter("m = {}\nm.a.b = 1".to_owned()),
+                   Err(Error::NotIndexable("Nihil".to_owned())));
+        assert_eq!(interpreter("x[0] = 1".to_owned()),
+                   Err(Error::VariableNotExists("x".to_owned())));
+        assert_eq!(interpreter("f = fungsi () { [1] }\nf()[0] = 1".to_owned()),
+                   Err(Error::Syntax("can not assign to f()[0]".to_owned())));
+    }
+
+    #[test]
+    fn test_native_values() {
+        match interpreter("fungsi tambah(a, b) { a + b }".to_owned()) {
+            Ok(Value::Function(closure)) => assert_eq!(closure.name(), "tambah"),
+            result => panic!("expected a function, found {:?}", result),
+        }
+        assert_eq!(interpreter("1 + 2".to_owned()), Ok(Value::Integer(3)));
+        assert_eq!(interpreter("3 / 2".to_owned()), Ok(Value::Float(1.5)));
+        assert_eq!(interpreter(":ok".to_owned()), Ok(Value::Atom(Atom::new("ok"))));
+    }
+
+    #[test]
+    fn test_map_keys() {
+        let script = r#"m = { 1 => "satu", benar => "ya", "1" => "teks" }
+                        m[2] = "dua"
+                        tulis m, " ", m[1], " ", m[benar]"#;
+        assert_eq!(output_of(script),
+                   r#"{"1" => "teks", 1 => "satu", 2 => "dua", benar => "ya"} satu ya"#);
+        assert_eq!(interpreter("{ 1.5 => 1 }".to_owned()), Err(Error::ExpectedIdentifier));
+        let script = "m = { (2 ** 64) => 1 }
+m[2 ** 64] = m[2 ** 64] + 1
+untuk k, v dalam m { tulis k, \" \", v }";
+        assert_eq!(output_of(script), "18446744073709551616 2");
+    }
+
+    #[test]
+    fn test_break_inside_expression() {
+        let script = "total = 0\nuntuk i dalam 1..5 { total = total + [i, jika i == 3 { berhenti }][0] }\ntotal";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(3)));
+        let script = "total = 0\nuntuk i dalam 0..4 { total = total + [i, jika i == 1 { lanjut }][0] }\ntotal";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(5)));
+    }
+
+    #[test]
+    fn test_closure_upvalues() {
+        let script = "fungsi penghitung() { n = 0\nfungsi () { n = n + 1 } }\n\
+                      c = penghitung()\nc()\nc()\nd = penghitung()\nd()\n[c(), d()]";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(vec![3, 2])));
+        let script = "fungsi luar() { a = 1\nfungsi tengah() { fungsi () { a = a + 10 } }\n\
+                      f = tengah()\nf()\na }\nluar()";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(11)));
+    }
+
+    #[test]
+    fn test_closure_does_not_keep_its_frame() {
+        // `turun` calls itself and lives in a slot of the frame it captures
+        // `x` from, so holding that frame would make a cycle.
+        let script = "fungsi luar(x) {\n\
+                      fungsi turun(n) { jika n > 0 { turun(n - 1) } lainnya { x } }\n\
+                      turun }\n\
+                      luar(7)";
+        let function = match interpreter(script.to_owned()) {
+            Ok(Value::Function(function)) => function,
+            value => panic!("expected a function, found {:?}", value),
+        };
+        assert_eq!(Rc::strong_count(&function), 1);
+        assert_eq!(interpreter(format!("{}\nt = luar(7)\nt(3)", script)), Ok(to_value(7)));
+        let script = "fungsi f() { 1 }\nfungsi luar() { fungsi f() { 2 }\nf = fungsi () { 3 }\nf() }\nluar()";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(3)));
+    }
+
+    #[test]
+    fn test_deep_recursion() {
+        let script = "fungsi turun(n) { jika n > 0 { turun(n - 1) + 1 } lainnya { 0 } }\nturun(5000)";
+        assert_eq!(interpreter(script.to_owned()), Ok(to_value(5000)));
+        let script = "fungsi terus(n) { terus(n + 1) }\nterus(0)";
+        assert_eq!(interpreter(script.to_owned()), Err(Error::CallTooDeep(vm::MAX_CALL_DEPTH)));
+    }
+
+    #[test]
+    fn test_globals_between_runs() {
+        let mut interpreter = Interpreter::new();
+        interpreter.run("a = 1\nfungsi tambah() { a = a + 1 }").unwrap();
+        interpreter.run("tambah()\ntambah()").unwrap();
+        assert_eq!(interpreter.run("a"), Ok(to_value(3)));
+    }
+}
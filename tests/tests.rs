@@ -2,12 +2,7 @@
 extern crate baik;
 mod tests {
     use baik::*;
-    use to_value;
-    use Error;
-    use Expr;
     use tree::Tree;
-    use Value;
-    use eval;
     use std::collections::HashMap;
 
     #[test]
@@ -42,7 +37,7 @@ mod tests {
 
     #[test]
     fn test_error_brackets_not_with_function() {
-        assert_eq!(eval("5 + ()"), Err(Error::BracketNotWithFunction));
+        assert_syntax_error("5 + ()");
     }
 
     #[test]
@@ -105,6 +100,7 @@ mod tests {
     #[test]
     fn test_len_string() {
         assert_eq!(eval("panjang('Halo Dunia!')"), Ok(to_value(11)));
+        assert_eq!(eval("panjang('héllo')"), Ok(to_value(5)));
     }
 
     #[test]
@@ -252,6 +248,329 @@ mod tests {
         assert_eq!(eval("23 % 5.5"), Ok(to_value(1.0)));
     }
 
+    #[test]
+    fn test_pow() {
+        assert_eq!(eval("2 ** 10"), Ok(to_value(1024)));
+        assert_eq!(eval("2 ** 3 ** 2"), Ok(to_value(512)));
+        assert_eq!(eval("(2 ** 3) ** 2"), Ok(to_value(64)));
+        assert_eq!(eval("2 * 3 ** 2"), Ok(to_value(18)));
+        assert_eq!(eval("2 ** -1"), Ok(to_value(0.5)));
+        assert_eq!(eval("9 ** 0.5"), Ok(to_value(3.0)));
+    }
+
+    #[test]
+    fn test_shift() {
+        assert_eq!(eval("1 << 4"), Ok(to_value(16)));
+        assert_eq!(eval("256 >> 4 + 1"), Ok(to_value(8)));
+        assert_eq!(eval("-16 >> 2"), Ok(to_value(-4)));
+        assert_eq!(eval("1 << 64"), Ok(Value::from(BigInt::from(1) << 64)));
+        assert_eq!(eval("1 << 4294967296"), Err(Error::InvalidShift("4294967296".to_owned())));
+        assert_eq!(eval("1 >> -1"), Err(Error::InvalidShift("-1".to_owned())));
+        assert_eq!(eval("1 << (2 ** 64)"), Err(Error::InvalidShift("18446744073709551616".to_owned())));
+        // Past `MAX_BITS` the result would take gigabytes.
+        assert_eq!(eval("1 << 4000000000"), Err(Error::IntegerOverflow("1 << 4000000000".to_owned())));
+        assert_eq!(eval("0 << 4000000000"), Ok(to_value(0)));
+        assert_eq!(eval("-1 >> 4000000000"), Ok(to_value(-1)));
+    }
+
+    #[test]
+    fn test_bitwise() {
+        assert_eq!(eval("6 & 3"), Ok(to_value(2)));
+        assert_eq!(eval("6 | 3"), Ok(to_value(7)));
+        assert_eq!(eval("6 ^ 3"), Ok(to_value(5)));
+        assert_eq!(eval("1 | 2 ^ 3 & 4"), Ok(to_value(3)));
+        assert_eq!(eval("1 | 2 == 3"), Ok(to_value(true)));
+        assert_eq!(
+            eval("1.5 & 1"),
+            Err(Error::UnsupportedTypes("1.5".to_owned(), "1".to_owned()))
+        );
+    }
+
+    fn big(digits: &str) -> Value {
+        Value::from(digits.parse::<BigInt>().unwrap())
+    }
+
+    #[test]
+    fn test_big_integer_promotion() {
+        assert_eq!(eval("9223372036854775807 + 1"), Ok(big("9223372036854775808")));
+        assert_eq!(eval("-9223372036854775807 - 2"), Ok(big("-9223372036854775809")));
+        assert_eq!(eval("4294967296 * 4294967296"), Ok(big("18446744073709551616")));
+        assert_eq!(eval("2 ** 100"), Ok(big("1267650600228229401496703205376")));
+        assert_eq!(eval("2 ** 62"), Ok(to_value(4611686018427387904i64)));
+        assert_eq!(eval("(-1) ** 9999999999"), Ok(to_value(-1)));
+        assert_eq!(
+            eval("2 ** 9999999999"),
+            Err(Error::IntegerOverflow("2 ** 9999999999".to_owned()))
+        );
+        assert_eq!(
+            eval("2 ** 4000000000"),
+            Err(Error::IntegerOverflow("2 ** 4000000000".to_owned()))
+        );
+        assert_eq!(eval("1 ** 4000000000"), Ok(to_value(1)));
+        assert_eq!(eval("pecahan(1, 3) ** 400000000"),
+                   Err(Error::IntegerOverflow("pecahan(1, 3) ** 400000000".to_owned())));
+        assert_eq!(eval("1.5d ** 400000000"), Err(Error::IntegerOverflow("1.5d ** 400000000".to_owned())));
+        assert_eq!(
+            Expr::new("x - 1").value("x", i64::MIN).exec(),
+            Ok(big("-9223372036854775809"))
+        );
+    }
+
+    #[test]
+    fn test_big_integer_back_to_integer() {
+        assert_eq!(eval("9223372036854775807 + 1 - 1"), Ok(to_value(9223372036854775807i64)));
+        assert_eq!(eval("2 ** 100 // 2 ** 98"), Ok(to_value(4)));
+        assert_eq!(eval("-9223372036854775808"), Ok(to_value(i64::MIN)));
+        assert_eq!(eval("jenis(2 ** 64)"), Ok(to_value("BilanganBesar")));
+        assert_eq!(eval("jenis(2 ** 64 - 2 ** 63 - 1)"), Ok(to_value("Angka")));
+    }
+
+    #[test]
+    fn test_big_integer_literal() {
+        assert_eq!(eval("18446744073709551615"), Ok(big("18446744073709551615")));
+        assert_eq!(eval("100_000_000_000_000_000_000"), Ok(big("100000000000000000000")));
+        assert_eq!(eval("0x1_0000_0000_0000_0000"), Ok(big("18446744073709551616")));
+        assert_eq!(eval("-0o2_000_000_000_000_000_000_000"), Ok(big("-18446744073709551616")));
+    }
+
+    #[test]
+    fn test_big_integer_operators() {
+        let x = "100000000000000000000";
+        let with_x = |raw: &str| eval(&raw.replace('x', &format!("({})", x)));
+        assert_eq!(with_x("x + x"), Ok(big("200000000000000000000")));
+        assert_eq!(with_x("x * x - 1"), Ok(big("9999999999999999999999999999999999999999")));
+        assert_eq!(with_x("x // 7"), Ok(big("14285714285714285714")));
+        assert_eq!(with_x("-x // 7"), Ok(big("-14285714285714285714")));
+        assert_eq!(with_x("x % 7"), Ok(to_value(2)));
+        assert_eq!(with_x("-x % 7"), Ok(to_value(-2)));
+        assert_eq!(with_x("x / 4"), Ok(to_value(25000000000000000000.0)));
+        assert_eq!(with_x("x ** 2"), Ok(big("10000000000000000000000000000000000000000")));
+        assert_eq!(with_x("x + 0.5"), Ok(to_value(100000000000000000000.5)));
+        assert_eq!(with_x("x % 0"), Err(Error::DivisionByZero));
+        assert_eq!(with_x("x & 65535"), Ok(to_value(0)));
+        assert_eq!(with_x("x | 1"), Ok(big("100000000000000000001")));
+        assert_eq!(with_x("x ^ x"), Ok(to_value(0)));
+        assert_eq!(with_x("-x & 1"), Ok(to_value(0)));
+        assert_eq!(with_x("x >> 20"), Ok(to_value(95367431640625i64)));
+        assert_eq!(with_x("-x >> 100"), Ok(to_value(-1)));
+        assert_eq!(with_x("x << 1"), Ok(big("200000000000000000000")));
+    }
+
+    #[test]
+    fn test_big_integer_comparison() {
+        let x = "100000000000000000000";
+        let with_x = |raw: &str| eval(&raw.replace('x', &format!("({})", x)));
+        assert_eq!(with_x("x > 9223372036854775807"), Ok(to_value(true)));
+        assert_eq!(with_x("-x < -9223372036854775808"), Ok(to_value(true)));
+        assert_eq!(with_x("x == 100000000000000000000"), Ok(to_value(true)));
+        assert_eq!(with_x("x == 100000000000000000000.0"), Ok(to_value(true)));
+        assert_eq!(with_x("x + 1 == 100000000000000000000.0"), Ok(to_value(false)));
+        assert_eq!(with_x("x + 1 > 100000000000000000000.0"), Ok(to_value(true)));
+        assert_eq!(with_x("x < 2.0 ** 2000"), Ok(to_value(true)));
+        assert_eq!(with_x("min(x ** 2, x, 2 ** 70)"), Ok(big(x)));
+        assert_eq!(with_x("untaian(x)"), Ok(to_value(vec![big(x)])));
+    }
+
+    #[test]
+    fn test_division() {
+        assert_eq!(eval("7 / 2"), Ok(to_value(3.5)));
+        assert_eq!(eval("6 / 2"), Ok(to_value(3.0)));
+        assert_eq!(eval("7 // 2"), Ok(to_value(3)));
+        assert_eq!(eval("-7 // 2"), Ok(to_value(-3)));
+        assert_eq!(eval("-7 % 2"), Ok(to_value(-1)));
+        assert_eq!(eval("-7 // 2 * 2 + -7 % 2"), Ok(to_value(-7)));
+        assert_eq!(eval("7.5 // 2"), Ok(to_value(3.0)));
+        assert_eq!(eval("1 + 7 // 2 * 2"), Ok(to_value(7)));
+        assert_eq!(
+            Expr::new("x // -1").value("x", i64::MIN).exec(),
+            Ok(Value::from(BigInt::from(i64::MIN) * -1))
+        );
+    }
+
+    #[test]
+    fn test_division_by_zero() {
+        assert_eq!(eval("1 / 0"), Err(Error::DivisionByZero));
+        assert_eq!(eval("1 // 0"), Err(Error::DivisionByZero));
+        assert_eq!(eval("1 % 0"), Err(Error::DivisionByZero));
+        assert_eq!(eval("1.5 / 0.0"), Err(Error::DivisionByZero));
+        assert_eq!(Expr::new("1 % x").value("x", 0).exec(), Err(Error::DivisionByZero));
+        assert_eq!(eval("0 ** -1"), Err(Error::DivisionByZero));
+        assert_eq!(eval("0.0 ** -0.5"), Err(Error::DivisionByZero));
+        assert_eq!(eval("0.0d ** -2"), Err(Error::DivisionByZero));
+        assert_eq!(Expr::new("x ** -2").value("x", 0).exec(), Err(Error::DivisionByZero));
+        assert_eq!(eval("0 ** 0"), Ok(to_value(1)));
+        // Too small for a float, but not zero.
+        assert_eq!(eval("1 / pecahan(1, 10 ** 400) == 10 ** 400"), Ok(to_value(true)));
+        assert_eq!(eval("1 % pecahan(1, 10 ** 400) == 0"), Ok(to_value(true)));
+        assert_eq!(eval("1 / pecahan(0, 10 ** 400)"), Err(Error::DivisionByZero));
+    }
+
+    #[test]
+    fn test_exact_mixed_comparison() {
+        assert_eq!(eval("9007199254740993 == 9007199254740992.0"), Ok(to_value(false)));
+        assert_eq!(eval("9007199254740993 > 9007199254740992.0"), Ok(to_value(true)));
+        assert_eq!(eval("9007199254740992.0 < 9007199254740993"), Ok(to_value(true)));
+        assert_eq!(eval("9007199254740992 == 9007199254740992.0"), Ok(to_value(true)));
+        assert_eq!(eval("9223372036854775807 < 9223372036854775808.0"), Ok(to_value(true)));
+        assert_eq!(eval("2 >= 1.5"), Ok(to_value(true)));
+        assert_eq!(eval("-2 < -1.5"), Ok(to_value(true)));
+        assert_eq!(eval("1 == 1.0"), Ok(to_value(true)));
+    }
+
+    fn decimal(text: &str) -> Value {
+        Value::Decimal(text.parse().unwrap())
+    }
+
+    fn written(result: Result<Value, Error>) -> String {
+        result.unwrap().to_string()
+    }
+
+    #[test]
+    fn test_decimal_literal() {
+        assert_eq!(written(eval("12.50d")), "12.50d");
+        assert_eq!(written(eval("-0.05d")), "-0.05d");
+        assert_eq!(written(eval("3d")), "3d");
+        assert_eq!(written(eval("1_000.00d")), "1000.00d");
+        assert_eq!(eval("jenis(12.50d)"), Ok(to_value("Desimal")));
+        assert_eq!(written(eval("Desimal(\"12.50\")")), "12.50d");
+        assert_eq!(written(eval("Desimal(7)")), "7d");
+        assert_eq!(written(eval("Desimal(0.1)")), "0.1d");
+        assert_eq!(eval("Desimal(\"12,5\")"), Err(Error::InvalidDecimal("12,5".to_owned())));
+    }
+
+    #[test]
+    fn test_decimal_arithmetic() {
+        assert_eq!(eval("0.1d + 0.2d == 0.3d"), Ok(to_value(true)));
+        assert_eq!(eval("0.1 + 0.2 == 0.3"), Ok(to_value(false)));
+        assert_eq!(written(eval("0.1d + 0.2d")), "0.3d");
+        assert_eq!(written(eval("12.50d + 0.5d")), "13.00d");
+        assert_eq!(written(eval("12.50d - 2")), "10.50d");
+        assert_eq!(written(eval("1.10d * 3")), "3.30d");
+        assert_eq!(written(eval("1.5d * 1.5d")), "2.25d");
+        assert_eq!(written(eval("-1.50d")), "-1.50d");
+        assert_eq!(written(eval("10.00d / 4")), "2.50d");
+        assert_eq!(written(eval("1d / 8")), "0.125d");
+        assert_eq!(written(eval("1d / 3")), "0.3333333333333333333333333333d");
+        assert_eq!(written(eval("2d / 3")), "0.6666666666666666666666666667d");
+        assert_eq!(written(eval("7.50d // 2")), "3d");
+        assert_eq!(written(eval("7.50d % 2")), "1.50d");
+        assert_eq!(written(eval("-7.50d % 2")), "-1.50d");
+        assert_eq!(written(eval("1.1d ** 2")), "1.21d");
+        assert_eq!(written(eval("2.0d ** -2")), "0.25d");
+        assert_eq!(eval("1.5d / 0"), Err(Error::DivisionByZero));
+        assert_eq!(eval("1.5d % 0.00d"), Err(Error::DivisionByZero));
+        assert_eq!(
+            eval("1.5d + 0.5"),
+            Err(Error::UnsupportedTypes("1.5d".to_owned(), "0.5".to_owned()))
+        );
+        assert_eq!(
+            eval("1.5d & 1"),
+            Err(Error::UnsupportedTypes("1.5d".to_owned(), "1".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_decimal_comparison() {
+        assert_eq!(eval("12.5d == 12.50d"), Ok(to_value(true)));
+        assert_eq!(eval("12.00d == 12"), Ok(to_value(true)));
+        assert_eq!(eval("0.5d == 0.5"), Ok(to_value(true)));
+        assert_eq!(eval("0.1d == 0.1"), Ok(to_value(false)));
+        assert_eq!(eval("0.1d < 0.1"), Ok(to_value(true)));
+        assert_eq!(eval("2.01d > 2"), Ok(to_value(true)));
+        assert_eq!(eval("-2.01d > 0 - 2 ** 70"), Ok(to_value(true)));
+        assert_eq!(eval("max(1.25d, 1.3d, 1)"), Ok(decimal("1.3")));
+    }
+
+    #[test]
+    fn test_decimal_rounding() {
+        assert_eq!(written(eval("bulatkan(0.125d, 2)")), "0.12d");
+        assert_eq!(written(eval("bulatkan(0.135d, 2)")), "0.14d");
+        assert_eq!(written(eval("bulatkan(0.125d, 2, :setengah_genap)")), "0.12d");
+        assert_eq!(written(eval("bulatkan(0.125d, 2, :setengah_atas)")), "0.13d");
+        assert_eq!(written(eval("bulatkan(-0.125d, 2, :setengah_atas)")), "-0.13d");
+        assert_eq!(written(eval("bulatkan(-0.125d, 2)")), "-0.12d");
+        assert_eq!(written(eval("bulatkan(0.1249d, 2, :setengah_atas)")), "0.12d");
+        assert_eq!(written(eval("bulatkan(12.5d, 2)")), "12.50d");
+        assert_eq!(written(eval("bulatkan(2.675, 2)")), "2.68d");
+        assert_eq!(written(eval("bulatkan(2500, 0)")), "2500d");
+        assert_eq!(written(eval("bulatkan(1d / 3, 4)")), "0.3333d");
+        assert_eq!(
+            eval("bulatkan(1.5d, 0, :ke_atas)"),
+            Err(Error::UnknownRounding("ke_atas".to_owned()))
+        );
+        assert_eq!(eval("bulatkan(1.5d, -1)"), Err(Error::ExpectedNumber));
+    }
+
+    #[test]
+    fn test_rational_lowest_terms() {
+        assert_eq!(written(eval("pecahan(1, 3)")), "pecahan(1, 3)");
+        assert_eq!(written(eval("pecahan(6, 4)")), "pecahan(3, 2)");
+        assert_eq!(written(eval("pecahan(2, -6)")), "pecahan(-1, 3)");
+        assert_eq!(written(eval("pecahan(4, 2)")), "pecahan(2, 1)");
+        assert_eq!(written(eval("pecahan(0.25d)")), "pecahan(1, 4)");
+        assert_eq!(written(eval("pecahan(1, 2 ** 70)")), "pecahan(1, 1180591620717411303424)");
+        assert_eq!(eval("jenis(pecahan(1, 3))"), Ok(to_value("Pecahan")));
+        assert_eq!(eval("pecahan(1, 0)"), Err(Error::DivisionByZero));
+        assert_eq!(eval("pecahan(0.5, 2)"), Err(Error::ExpectedNumber));
+    }
+
+    #[test]
+    fn test_rational_arithmetic() {
+        assert_eq!(eval("pecahan(1, 3) + pecahan(1, 6) == pecahan(1, 2)"), Ok(to_value(true)));
+        assert_eq!(written(eval("pecahan(1, 3) + pecahan(2, 3)")), "pecahan(1, 1)");
+        assert_eq!(written(eval("pecahan(1, 3) - 1")), "pecahan(-2, 3)");
+        assert_eq!(written(eval("pecahan(2, 3) * 6")), "pecahan(4, 1)");
+        assert_eq!(written(eval("pecahan(2, 3) / 4")), "pecahan(1, 6)");
+        assert_eq!(written(eval("1 / pecahan(2, 3)")), "pecahan(3, 2)");
+        assert_eq!(written(eval("-pecahan(2, 3)")), "pecahan(-2, 3)");
+        assert_eq!(written(eval("pecahan(7, 2) // 2")), "pecahan(1, 1)");
+        assert_eq!(written(eval("pecahan(-7, 2) % 2")), "pecahan(-3, 2)");
+        assert_eq!(written(eval("pecahan(2, 3) ** 3")), "pecahan(8, 27)");
+        assert_eq!(written(eval("pecahan(2, 3) ** -2")), "pecahan(9, 4)");
+        assert_eq!(written(eval("pecahan(1, 2) + 0.25d")), "pecahan(3, 4)");
+        assert_eq!(written(eval("pecahan(1, 3) * 2 ** 64")), "pecahan(18446744073709551616, 3)");
+        assert_eq!(eval("pecahan(1, 3) / 0"), Err(Error::DivisionByZero));
+        assert_eq!(eval("pecahan(0, 1) ** -1"), Err(Error::DivisionByZero));
+        assert_eq!(
+            eval("pecahan(1, 2) + 0.5"),
+            Err(Error::UnsupportedTypes("pecahan(1, 2)".to_owned(), "0.5".to_owned()))
+        );
+        assert_eq!(
+            eval("pecahan(1, 2) | 1"),
+            Err(Error::UnsupportedTypes("pecahan(1, 2)".to_owned(), "1".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_rational_comparison() {
+        assert_eq!(eval("pecahan(2, 6) == pecahan(1, 3)"), Ok(to_value(true)));
+        assert_eq!(eval("pecahan(4, 2) == 2"), Ok(to_value(true)));
+        assert_eq!(eval("pecahan(1, 2) == 0.5"), Ok(to_value(true)));
+        assert_eq!(eval("pecahan(1, 2) == 0.50d"), Ok(to_value(true)));
+        assert_eq!(eval("pecahan(1, 3) == 1 / 3"), Ok(to_value(false)));
+        assert_eq!(eval("pecahan(1, 3) < 0.3333333333333333"), Ok(to_value(false)));
+        assert_eq!(eval("pecahan(1, 3) < 0.34d"), Ok(to_value(true)));
+        assert_eq!(eval("pecahan(-1, 3) > 0 - 2 ** 70"), Ok(to_value(true)));
+        assert_eq!(written(eval("max(pecahan(1, 3), pecahan(1, 4))")), "pecahan(1, 3)");
+    }
+
+    #[test]
+    fn test_float_conversion() {
+        assert_eq!(eval("ke_float(pecahan(1, 4))"), Ok(to_value(0.25)));
+        assert_eq!(eval("ke_float(pecahan(1, 3)) == 1 / 3"), Ok(to_value(true)));
+        assert_eq!(eval("ke_float(12.50d)"), Ok(to_value(12.5)));
+        assert_eq!(eval("ke_float(3)"), Ok(to_value(3.0)));
+        assert_eq!(eval("ke_float(\"3\")"), Err(Error::ExpectedNumber));
+    }
+
+    #[test]
+    fn test_unary_minus_and_plus() {
+        assert_eq!(eval("-5"), Ok(to_value(-5)));
+        assert_eq!(eval("-5 + +2"), Ok(to_value(-3)));
+        assert_eq!(eval("3 - -(1 + 1)"), Ok(to_value(5)));
+        assert_eq!(Expr::new("-x * 2").value("x", 1.5).exec(), Ok(to_value(-3.0)));
+    }
+
     #[test]
     fn test_and_1() {
         assert_eq!(eval("3 > 2 && 2 > 1"), Ok(to_value(true)));
@@ -287,6 +606,17 @@ mod tests {
         assert_eq!(eval("3 < 2 || 2 > 1"), Ok(to_value(true)));
     }
 
+    #[test]
+    fn test_and_or_short_circuit() {
+        assert_eq!(eval("salah && 1 / 0 == 1"), Ok(to_value(false)));
+        assert_eq!(eval("benar || 1 / 0 == 1"), Ok(to_value(true)));
+        assert_eq!(
+            Expr::new("panjang(a) == 1 || 1 / a[0] > 0").value("a", vec![0]).exec(),
+            Ok(to_value(true))
+        );
+        assert_eq!(eval("benar && 1 / 0 == 1"), Err(Error::DivisionByZero));
+    }
+
     #[test]
     fn test_not() {
         assert_eq!(eval("!false"), Ok(to_value(true)));
@@ -391,68 +721,123 @@ mod tests {
         );
     }
 
+    fn assert_syntax_error(raw: &str) {
+        match Tree::new(raw).parse_node() {
+            Err(Error::Syntax(_)) => (),
+            result => panic!("expected a syntax error for {:?}, found: {:?}", raw, result),
+        }
+    }
+
     #[test]
     fn test_error_start_with_non_value_operator() {
-        let mut tree = Tree {
-            raw: "+ + 5".to_owned(),
-            ..Default::default()
-        };
-
-        tree.parse_pos().unwrap();
-        tree.parse_operators().unwrap();
-
-        assert_eq!(tree.parse_node(), Err(Error::StartWithNonValueOperator));
+        assert_syntax_error("* + 5");
     }
 
     #[test]
     fn test_error_duplicate_operator() {
-        let mut tree = Tree {
-            raw: "5 + + 5".to_owned(),
-            ..Default::default()
-        };
+        assert_syntax_error("5 + * 5");
+    }
 
-        tree.parse_pos().unwrap();
-        tree.parse_operators().unwrap();
+    #[test]
+    fn test_error_duplicate_value() {
+        assert_syntax_error("2 + 6 5");
+    }
 
-        assert_eq!(tree.parse_node(), Err(Error::DuplicateOperatorNode));
+    #[test]
+    fn test_error_unpaired_brackets() {
+        assert_syntax_error("(2 + 3)) * 5");
+        assert_syntax_error("((2 + 3) * 5");
     }
 
     #[test]
-    fn test_error_duplicate_value() {
-        let mut tree = Tree {
-            raw: "2 + 6 5".to_owned(),
-            ..Default::default()
-        };
+    fn test_error_comma() {
+        assert_syntax_error(", 2 + 5");
+    }
 
-        tree.parse_pos().unwrap();
-        tree.parse_operators().unwrap();
+    fn optimized(raw: &str) -> Tree {
+        let mut tree = Tree::new(raw);
+        tree.parse_node().unwrap();
+        tree.optimize();
+        tree
+    }
 
-        assert_eq!(tree.parse_node(), Err(Error::DuplicateValueNode));
+    #[test]
+    fn test_optimize_folds_constants() {
+        assert_eq!(optimized("(2 + 3) * x").node, optimized("5 * x").node);
+        assert_eq!(optimized("x + (2 * 3 - 1)").node, optimized("x + 5").node);
+        assert_eq!(optimized("!(1 > 2)").node, optimized("!(3 > 4)").node);
     }
 
     #[test]
-    fn test_error_unpaired_brackets() {
-        let mut tree = Tree {
-            raw: "(2 + 3)) * 5".to_owned(),
-            ..Default::default()
-        };
+    fn test_optimize_removes_brackets() {
+        assert_eq!(optimized("((x))").node, optimized("x").node);
+        assert_eq!(optimized("(x + y) == z").node, optimized("x + y == z").node);
+    }
 
-        tree.parse_pos().unwrap();
+    #[test]
+    fn test_optimize_pure_builtins() {
+        assert_ne!(optimized("min(1, 2)").node, optimized("min(a, 2)").node);
+        assert_eq!(eval("min(4, 2, 3) + max(1, 6)"), Ok(to_value(8)));
+        assert_eq!(eval("panjang(\"abc\") * 2"), Ok(to_value(6)));
+    }
 
-        assert_eq!(tree.parse_operators(), Err(Error::UnpairedBrackets));
+    #[test]
+    fn test_optimize_keeps_custom_functions() {
+        assert_eq!(
+            Expr::new("min(1, 2)")
+                .function("min", |_| Ok(to_value(42)))
+                .exec(),
+            Ok(to_value(42))
+        );
     }
 
     #[test]
-    fn test_error_comma() {
-        let mut tree = Tree {
-            raw: ", 2 + 5".to_owned(),
-            ..Default::default()
-        };
+    fn test_optimize_errors_at_exec() {
+        let expr = Expr::new("panjang(1) + 1 / 0").compile();
+        assert!(expr.is_ok());
+        assert!(expr.unwrap().exec().is_err());
+        assert!(Expr::new("\"a\" - 1").compile().is_ok());
+    }
 
-        tree.parse_pos().unwrap();
-        tree.parse_operators().unwrap();
+    #[test]
+    fn test_same_language_as_scripts() {
+        let sources = [
+            "2 + 3 * 4 - -1",
+            "(1 + 2) * 3 % 4",
+            "10 - 4 - 3",
+            "'satu' + \"dua\" + 'it\\'s'",
+            "benar && !salah || salah == benar",
+            "[1, 2, 3][1]",
+            "panjang([1, 2, 3]) + min(0..5) + max(2, 7)",
+            "1 < 2 == (3 >= 3)",
+            ":atom",
+            "2 ** 3",
+            "-2 ** 2",
+            "1 << 2",
+            "6 ^ 3",
+        ];
+        for source in sources.iter() {
+            assert_eq!(eval(source), core::Interpreter::new().run(source), "{}", source);
+        }
+    }
 
-        assert_eq!(tree.parse_node(), Err(Error::CommaNotWithFunction));
+    #[test]
+    fn test_english_booleans_only_in_expr() {
+        assert_eq!(eval("true && !false"), Ok(to_value(true)));
+        assert_eq!(core::Interpreter::new().run("true = 1\nfalse = true + 1\n[true, false]"),
+                   Ok(to_value(vec![1, 2])));
+    }
+
+    #[test]
+    fn test_script_only_expressions() {
+        assert_eq!(
+            Expr::new("x = 1").compile().map(|_| ()),
+            Err(Error::UnsupportedExpression("x = 1".to_owned()))
+        );
+        assert_eq!(
+            Expr::new("1 + jika x { 2 }").compile().map(|_| ()),
+            Err(Error::UnsupportedExpression("jika x { 2 }".to_owned()))
+        );
     }
 
     #[test]
@@ -478,50 +863,14 @@ mod benches {
     }
 
     #[bench]
-    fn bench_parse_pos(b: &mut test::Bencher) {
-        let mut tree = Tree {
-            raw: "(2 + (3 + 4) + (6 + (6 + 7)) + 5)".to_owned(),
-            ..Default::default()
-        };
-
-        b.iter(|| tree.parse_pos().unwrap());
-    }
-
-    #[bench]
-    fn bench_parse_operators(b: &mut test::Bencher) {
-        let mut tree = Tree {
-            raw: "(2 + (3 + 4) + (6 + (6 + 7)) + 5)".to_owned(),
-            ..Default::default()
-        };
-
-        tree.parse_pos().unwrap();
-        b.iter(|| tree.parse_operators().unwrap());
-    }
-
-    #[bench]
-    fn bench_parse_nodes(b: &mut test::Bencher) {
-        let mut tree = Tree {
-            raw: "(2 + (3 + 4) + (6 + (6 + 7)) + 5)".to_owned(),
-            ..Default::default()
-        };
-
-        tree.parse_pos().unwrap();
-        tree.parse_operators().unwrap();
+    fn bench_parse_node(b: &mut test::Bencher) {
+        let mut tree = Tree::new("(2 + (3 + 4) + (6 + (6 + 7)) + 5)");
         b.iter(|| tree.parse_node().unwrap());
     }
 
     #[bench]
     fn bench_compile(b: &mut test::Bencher) {
-        b.iter(|| {
-            let mut tree = Tree {
-                raw: "(2 + (3 + 4) + (6 + (6 + 7)) + 5)".to_owned(),
-                ..Default::default()
-            };
-            tree.parse_pos().unwrap();
-            tree.parse_operators().unwrap();
-            tree.parse_node().unwrap();
-            tree.compile().unwrap();
-        });
+        b.iter(|| Tree::new("(2 + (3 + 4) + (6 + (6 + 7)) + 5)").compile().unwrap());
     }
 
     #[bench]
@@ -536,4 +885,18 @@ mod benches {
     fn bench_eval(b: &mut test::Bencher) {
         b.iter(|| eval("(2 + (3 + 4) + (6 + (6 + 7)) + 5)"));
     }
-}
\ No newline at end of file
+
+    #[bench]
+    fn bench_script_loop(b: &mut test::Bencher) {
+        let program = baik::ast::parse("jumlah = 0\nuntuk i dalam 0..10000 { jumlah = jumlah + i * 2 }\njumlah")
+            .unwrap();
+        b.iter(|| baik::core::Interpreter::new().exec(&program).unwrap());
+    }
+
+    #[bench]
+    fn bench_script_calls(b: &mut test::Bencher) {
+        let program = baik::ast::parse("fungsi fib(n) { jika n < 2 { kembali n }\nfib(n - 1) + fib(n - 2) }\nfib(15)")
+            .unwrap();
+        b.iter(|| baik::core::Interpreter::new().exec(&program).unwrap());
+    }
+}
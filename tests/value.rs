@@ -0,0 +1,76 @@
+#[cfg(test)]
+extern crate baik;
+#[macro_use]
+extern crate serde_json;
+mod tests {
+    use baik::*;
+    use baik::core::interpreter;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn test_from_json() {
+        let json = json!({ "a": [1, -2, 1.5, "x", null, true] });
+        let value = Value::from(json.clone());
+        match value {
+            Value::Map(ref map) => assert_eq!(map.len(), 1),
+            ref value => panic!("expected a map, found {:?}", value),
+        }
+        assert_eq!(value.to_json(), Ok(json.clone()));
+        assert_eq!(serde_json::Value::try_from(value), Ok(json));
+    }
+
+    #[test]
+    fn test_integers_stay_exact() {
+        let value = Value::from(json!(9007199254740993i64));
+        assert_eq!(value.as_i64(), Some(9007199254740993));
+        assert_eq!(value.to_json(), Ok(json!(9007199254740993i64)));
+        assert_eq!(Value::from(json!(1.0)).to_json(), Ok(json!(1.0)));
+    }
+
+    #[test]
+    fn test_exact_numbers_to_json() {
+        let text = "[18446744073709551615,-123456789012345678901234567890]";
+        let json: serde_json::Value = serde_json::from_str(text).unwrap();
+        let value = Value::from(json.clone());
+        let big = "-123456789012345678901234567890".parse::<BigInt>().unwrap();
+        assert_eq!(value, Value::Array(vec![Value::from(u64::MAX), Value::from(big)]));
+        assert_eq!(value.to_json(), Ok(json));
+        assert_eq!(value.to_json().unwrap().to_string(), text);
+        let to_json = |script: &str| interpreter(script.to_owned()).unwrap().to_json();
+        assert_eq!(to_json("12.50d").unwrap().to_string(), "12.50");
+        assert_eq!(to_json("pecahan(-3, 8)").unwrap().to_string(), "-0.375");
+        assert_eq!(to_json("pecahan(1, 3)"), Err(Error::UnexpectedNumber("pecahan(1, 3)".to_owned())));
+        assert_eq!(Value::Float(f64::NAN).to_json(), Err(Error::UnexpectedNumber("NaN".to_owned())));
+    }
+
+    #[test]
+    fn test_to_json_errors() {
+        assert_eq!(interpreter(":a".to_owned()).unwrap().to_json(),
+                   Err(Error::UnexpectedAtom("a".to_owned())));
+        assert_eq!(interpreter("fungsi f() { 1 }".to_owned()).unwrap().to_json(),
+                   Err(Error::UnexpectedFunction("f".to_owned())));
+        assert_eq!(interpreter("{ 1 => 2 }".to_owned()).unwrap().to_json(),
+                   Err(Error::UnexpectedKey("1".to_owned())));
+    }
+
+    #[test]
+    fn test_instances_to_json() {
+        let script = "type Titik(x, y) maka selesai\nTitik{ x: 1, y: [2] }";
+        assert_eq!(interpreter(script.to_owned()).unwrap().to_json(),
+                   Ok(json!({ "x": 1, "y": [2] })));
+    }
+
+    #[test]
+    fn test_bytes() {
+        let value = interpreter("Bita(\"hi\") + Bita([0, 255])".to_owned()).unwrap();
+        assert_eq!(value, Value::Bytes(vec![104, 105, 0, 255]));
+        assert_eq!(value.to_string(), "Bita([104, 105, 0, 255])");
+        assert_eq!(value.type_name(), "Bita");
+        assert_eq!(value.to_json(), Ok(json!([104, 105, 0, 255])));
+        assert_eq!(Value::from(vec![104u8, 105]), Value::Bytes(vec![104, 105]));
+        assert!(Value::Bytes(vec![1]) != Value::Array(vec![Value::Integer(1)]));
+        assert_eq!(interpreter("Bita([1, 256])".to_owned()),
+                   Err(Error::InvalidByte(Value::Integer(256))));
+        assert_eq!(interpreter("Bita(1)".to_owned()), Err(Error::ExpectedArray));
+    }
+}